@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use headless_haven_helper::State;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = headless_haven_helper::from_bytes::<State>(data);
+});
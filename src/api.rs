@@ -0,0 +1,792 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Bytes, Message, WebSocket};
+
+use crate::command::{Command, CommandError, CommandSender};
+use crate::diff::Target;
+use crate::level::Difficulty;
+use crate::modifiers::DrawMode;
+use crate::round::RoundError;
+use crate::session::unix_millis;
+use crate::state::{CharacterClass, State};
+
+/// How long a client has to send its request before the connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// The most bytes read for the request line and headers of a request.
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+/// The latest state received, along with when it arrived.
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    pub message_number: i32,
+    /// Milliseconds since the Unix epoch.
+    pub received_at: u64,
+    pub state: State,
+}
+
+/// An HTTP API serving the latest [`State`] as JSON.
+///
+/// | Path                    | Body                                                           |
+/// |-------------------------|----------------------------------------------------------------|
+/// | `/state`                | The whole state                                                |
+/// | `/state/actors`         | The players and monsters, in the order the helper sent them    |
+/// | `/state/elements`       | Each element's state, keyed by element name                    |
+/// | `/state/modifiers`      | The attack modifier deck, its discard pile and `needs_shuffle` |
+/// | `/state/modifiers/odds` | The odds of the next monster attack, see below                 |
+/// | `/state/scenario`       | The scenario being played and what follows from its level      |
+/// | `/state/level`          | The recommended scenario level, see below                      |
+/// | `/state/decks/{id}`     | The monster ability deck with the given id and its shown card  |
+///
+/// `/state/level` compares the scenario level to the one recommended for the party, at the
+/// difficulty given by the `difficulty` query parameter: `easy`, `normal` (the default), `hard`
+/// or `very-hard`.
+///
+/// `/state/modifiers/odds` requires the base attack value as the `attack` query parameter, and
+/// takes the draw mode as `mode`: `normal` (the default), `advantage` or `disadvantage`. Given
+/// the `hp` of a target, the response also has the `kill_chance` of the attack.
+///
+/// Every response is an object that also has the `message_number` and `received_at` of the
+/// [`Snapshot`] it was derived from. Until a state has been published every path responds with
+/// `503 Service Unavailable`.
+///
+/// WebSocket clients connecting to `/ws` are sent the latest snapshot, serialized like the
+/// `/state` response, as soon as they connect and then again every time a state is published.
+/// Requests for `/ws` that don't ask for a WebSocket upgrade get `400 Bad Request`.
+///
+/// An API created [`with_commands`](Api::with_commands) also runs the round, sending the
+/// [`Command`] for each `POST` request to the helper server:
+///
+/// | Path                    | Command                                                        |
+/// |-------------------------|----------------------------------------------------------------|
+/// | `/round/start`          | [`Command::StartRound`]                                        |
+/// | `/round/end-turn`       | [`Command::EndTurn`] for the figure given in the query         |
+/// | `/round/end`            | [`Command::EndRound`]                                          |
+///
+/// `/round/end-turn` takes a player as `player=Brute`, a summon as `owner=Mindthief&summon=1`
+/// or a monster standee as `monster=19&number=7`. The response has the `message_number` the
+/// resulting state was sent with.
+#[derive(Clone, Default)]
+pub struct Api {
+    latest: Arc<RwLock<Option<Snapshot>>>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    commands: Option<CommandSender>,
+}
+
+/// Passes a published snapshot on, returning whether the subscriber is still listening.
+type Subscriber = Box<dyn Fn(&Arc<String>) -> bool + Send + Sync>;
+
+impl Api {
+    pub fn new() -> Self {
+        Api::default()
+    }
+
+    /// An API that also accepts commands, sending them with `commands`.
+    pub fn with_commands(commands: CommandSender) -> Self {
+        Api {
+            commands: Some(commands),
+            ..Api::default()
+        }
+    }
+
+    /// Makes `state` the one served from now on and pushes it to every WebSocket client.
+    pub fn publish(&self, message_number: i32, state: State) {
+        let snapshot = Snapshot {
+            message_number,
+            received_at: unix_millis(),
+            state,
+        };
+        // Hold the subscribers while replacing the snapshot, so a client that subscribes
+        // concurrently gets each snapshot exactly once.
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let message = Arc::new(serde_json::to_string(&snapshot).unwrap());
+        *self.latest.write().unwrap() = Some(snapshot);
+        subscribers.retain(|subscriber| subscriber(&message));
+    }
+
+    pub fn latest(&self) -> Option<Snapshot> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// Returns a receiver for every snapshot published from now on, preceded by the latest one
+    /// if there is one.
+    pub fn subscribe(&self) -> Receiver<Arc<String>> {
+        let (sender, receiver) = mpsc::channel();
+        self.add_subscriber(Box::new(move |message| {
+            sender.send(message.clone()).is_ok()
+        }));
+        receiver
+    }
+
+    fn add_subscriber(&self, subscriber: Subscriber) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if let Some(snapshot) = &*self.latest.read().unwrap() {
+            if !subscriber(&Arc::new(serde_json::to_string(snapshot).unwrap())) {
+                return;
+            }
+        }
+        subscribers.push(subscriber);
+    }
+
+    /// Serves the API on `addr` until the listener fails.
+    pub fn serve<A>(&self, addr: A) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        A: ToSocketAddrs,
+    {
+        self.serve_listener(TcpListener::bind(addr)?)
+    }
+
+    /// Serves the API on an already bound listener until it fails, handling each connection on
+    /// a thread of its own.
+    pub fn serve_listener(
+        &self,
+        listener: TcpListener,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let api = self.clone();
+            std::thread::spawn(move || {
+                if let Err(err) = api.respond(stream) {
+                    eprintln!("unable to respond to HTTP request: {}", err);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads a single request from `stream` and responds to it, closing the connection
+    /// afterwards unless it's upgraded to a WebSocket.
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let request = match Request::read(&mut reader) {
+            Ok(request) => request,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                return write_response(&mut stream, bad_request(&err.to_string()));
+            }
+            Err(err) => return Err(err),
+        };
+        if request.path() == "/ws" {
+            let buffered = reader.buffer().to_vec();
+            return self.accept_websocket(stream, buffered, &request);
+        }
+        write_response(&mut stream, self.handle(&request.method, &request.url))
+    }
+
+    /// Completes the WebSocket handshake for `request` and pushes snapshots to the client until
+    /// it disconnects. The socket is read on a thread of its own, which hands pings and the
+    /// client closing the connection over to this one, so that only one thread writes to it.
+    fn accept_websocket(
+        &self,
+        mut stream: TcpStream,
+        buffered: Vec<u8>,
+        request: &Request,
+    ) -> io::Result<()> {
+        let key = match request.header("Sec-WebSocket-Key") {
+            Some(key) if request.is_websocket_upgrade() => derive_accept_key(key.as_bytes()),
+            _ => return write_response(&mut stream, bad_request("expected a WebSocket upgrade")),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            key
+        )?;
+        stream.set_read_timeout(None)?;
+
+        let (sender, outgoing) = mpsc::channel();
+        let reader = WebSocket::from_partially_read(
+            ReadHalf(stream.try_clone()?),
+            buffered,
+            Role::Server,
+            None,
+        );
+        {
+            let sender = sender.clone();
+            std::thread::spawn(move || read_websocket(reader, sender));
+        }
+        self.add_subscriber(Box::new(move |message| {
+            sender.send(Outgoing::Snapshot(message.clone())).is_ok()
+        }));
+
+        let socket = WebSocket::from_raw_socket(stream.try_clone()?, Role::Server, None);
+        let result = write_websocket(socket, outgoing);
+        // Also stops the reading thread if it's this side that gave up.
+        let _ = stream.shutdown(Shutdown::Both);
+        match result {
+            Ok(()) | Err(tungstenite::Error::ConnectionClosed) => Ok(()),
+            Err(err) => Err(io::Error::other(err)),
+        }
+    }
+
+    /// Routes a request, returning the status code and body of the response.
+    pub fn handle(&self, method: &str, url: &str) -> (u16, Value) {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+        match (method, &self.commands) {
+            ("GET", _) => {}
+            ("POST", Some(commands)) => return handle_command(commands, &segments, query),
+            _ => return (405, json!({ "error": "method not allowed" })),
+        }
+        if segments[0] != "state" {
+            return not_found();
+        }
+
+        let latest = self.latest.read().unwrap();
+        let snapshot = match &*latest {
+            Some(snapshot) => snapshot,
+            None => return (503, json!({ "error": "no state received yet" })),
+        };
+        let state = &snapshot.state;
+        let mut body = match segments[1..] {
+            [] => json!({ "state": state }),
+            ["actors"] => json!({ "actors": state.actors }),
+            ["elements"] => {
+                let elements: Map<_, _> = state
+                    .elements()
+                    .iter()
+                    .map(|(element, state)| (element.name().to_owned(), json!(state)))
+                    .collect();
+                json!({ "elements": elements })
+            }
+            ["modifiers"] => json!({
+                "attack_modifiers": state.attack_modifiers,
+                "attack_modifiers_discard": state.attack_modifiers_discard,
+                "needs_shuffle": state.needs_shuffle,
+            }),
+            ["modifiers", "odds"] => {
+                let attack = match query_param(query, "attack").map(|value| value.parse::<i32>()) {
+                    Some(Ok(attack)) => attack,
+                    _ => return bad_request("expected an integer attack parameter"),
+                };
+                let mode = match query_param(query, "mode").map(|value| value.parse::<DrawMode>()) {
+                    Some(Ok(mode)) => mode,
+                    Some(Err(err)) => return bad_request(&err),
+                    None => DrawMode::Normal,
+                };
+                let hp = match query_param(query, "hp").map(|value| value.parse::<i32>()) {
+                    Some(Ok(hp)) => Some(hp),
+                    Some(Err(_)) => return bad_request("expected an integer hp parameter"),
+                    None => None,
+                };
+                let odds = state.modifier_odds(attack, mode);
+                let mut body = json!({ "odds": odds });
+                if let (Some(odds), Some(hp)) = (&odds, hp) {
+                    body["kill_chance"] = odds.at_least(hp).into();
+                }
+                body
+            }
+            ["scenario"] => json!({
+                "scenario": state.scenario(),
+                "level": state.level_info(),
+                "description": state.describe_scenario(),
+            }),
+            ["level"] => {
+                let difficulty = match query_param(query, "difficulty")
+                    .map(|value| value.parse::<Difficulty>())
+                {
+                    Some(Ok(difficulty)) => difficulty,
+                    Some(Err(err)) => return bad_request(&err),
+                    None => Difficulty::Normal,
+                };
+                json!({ "recommendation": state.level_recommendation(difficulty) })
+            }
+            ["decks", id] => {
+                let deck = id
+                    .parse()
+                    .ok()
+                    .and_then(|id: i32| state.ability_decks.iter().find(|deck| deck.id == id));
+                match deck {
+                    Some(deck) => json!({ "deck": deck, "shown_card": deck.shown_card() }),
+                    None => return not_found(),
+                }
+            }
+            _ => return not_found(),
+        };
+        body["message_number"] = snapshot.message_number.into();
+        body["received_at"] = snapshot.received_at.into();
+        (200, body)
+    }
+}
+
+/// Sends the command for a `POST` request to `segments`.
+fn handle_command(commands: &CommandSender, segments: &[&str], query: &str) -> (u16, Value) {
+    let command = match segments {
+        ["round", "start"] => Command::StartRound,
+        ["round", "end-turn"] => match target(query) {
+            Some(target) => Command::EndTurn(target),
+            None => return bad_request("expected a player, summon or monster standee"),
+        },
+        ["round", "end"] => Command::EndRound,
+        _ => return not_found(),
+    };
+    match commands.send(&command) {
+        Ok(message_number) => (200, json!({ "message_number": message_number })),
+        Err(err) => {
+            let status = match err {
+                CommandError::UnknownTarget(_)
+                | CommandError::Round(RoundError::UnknownTarget(_)) => 404,
+                CommandError::Round(_) | CommandError::EmptyModifierDeck => 409,
+                CommandError::NotConnected => 503,
+                CommandError::State(_) | CommandError::Io(_) => 500,
+            };
+            (status, json!({ "error": err.to_string() }))
+        }
+    }
+}
+
+/// Finds the figure given by the `player`, `owner` and `summon`, or `monster` and `number`
+/// parameters of a query string.
+fn target(query: &str) -> Option<Target> {
+    let class = |name| serde_json::from_value::<CharacterClass>(json!(name)).ok();
+    let number = |name| query_param(query, name)?.parse().ok();
+    if let Some(player) = query_param(query, "player") {
+        Some(Target::Player(class(player)?))
+    } else if let Some(owner) = query_param(query, "owner") {
+        Some(Target::Summon {
+            owner: class(owner)?,
+            number: number("summon")?,
+        })
+    } else {
+        Some(Target::MonsterInstance {
+            monster_id: number("monster")?,
+            number: number("number")?,
+        })
+    }
+}
+
+/// Finds the value of parameter `name` in a query string, percent-decoded.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| percent_decode(key) == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Decodes `%XX` escapes and `+` for spaces the way browsers encode query strings. Escapes
+/// that aren't followed by two hex digits are kept as they are.
+fn percent_decode(encoded: &str) -> String {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+                continue;
+            }
+            (b'+', _) => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// What the thread writing to a WebSocket connection sends to the client.
+enum Outgoing {
+    Snapshot(Arc<String>),
+    /// The reply to a ping with this payload.
+    Pong(Bytes),
+    /// The reply to the client closing the connection, which ends it. Also sent when it
+    /// couldn't be read any more.
+    Close,
+}
+
+/// Reads `socket` until the client closes it, passing what has to be replied on to the thread
+/// writing to it.
+fn read_websocket(mut socket: WebSocket<ReadHalf>, sender: Sender<Outgoing>) {
+    loop {
+        let message = match socket.read() {
+            Ok(Message::Ping(data)) => Outgoing::Pong(data),
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        if sender.send(message).is_err() {
+            return;
+        }
+    }
+    let _ = sender.send(Outgoing::Close);
+}
+
+/// Sends `outgoing` to the client until the connection is closed.
+fn write_websocket(
+    mut socket: WebSocket<TcpStream>,
+    outgoing: Receiver<Outgoing>,
+) -> tungstenite::Result<()> {
+    for message in outgoing {
+        match message {
+            Outgoing::Snapshot(snapshot) => socket.send(Message::text(snapshot.as_str()))?,
+            Outgoing::Pong(data) => socket.send(Message::Pong(data))?,
+            Outgoing::Close => {
+                socket.close(None)?;
+                return socket.flush();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The reading half of a WebSocket connection. Whatever tungstenite writes to it on its own,
+/// such as pongs, is dropped, as the writing half sends those replies instead.
+struct ReadHalf(TcpStream);
+
+impl Read for ReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for ReadHalf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn bad_request(error: &str) -> (u16, Value) {
+    (400, json!({ "error": error }))
+}
+
+fn not_found() -> (u16, Value) {
+    (404, json!({ "error": "not found" }))
+}
+
+/// The request line and headers of an HTTP request. Requests with a body aren't supported, so
+/// it isn't read.
+struct Request {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Reads the request line and headers, failing with `InvalidData` if they're malformed or
+    /// longer than [`MAX_REQUEST_HEAD`].
+    fn read<R: BufRead>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+        let mut head = reader.take(MAX_REQUEST_HEAD);
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if head.read_line(&mut line)? == 0 {
+                return Err(invalid("incomplete request"));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            lines.push(line.to_owned());
+        }
+
+        let request_line = lines.first().ok_or_else(|| invalid("empty request"))?;
+        let mut request_line = request_line.split(' ');
+        let (method, url) = match (request_line.next(), request_line.next()) {
+            (Some(method), Some(url)) if !url.is_empty() => (method.to_owned(), url.to_owned()),
+            _ => return Err(invalid("malformed request line")),
+        };
+        let headers = lines[1..]
+            .iter()
+            .map(|line| {
+                let (name, value) = line
+                    .split_once(':')
+                    .ok_or_else(|| invalid("malformed header"))?;
+                Ok((name.trim().to_owned(), value.trim().to_owned()))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Request {
+            method,
+            url,
+            headers,
+        })
+    }
+
+    fn path(&self) -> &str {
+        self.url.split('?').next().unwrap_or_default()
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether this is a `GET` request asking to upgrade the connection to a WebSocket.
+    fn is_websocket_upgrade(&self) -> bool {
+        let has_token = |name, token: &str| {
+            self.header(name).is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|value| value.trim().eq_ignore_ascii_case(token))
+            })
+        };
+        self.method == "GET"
+            && has_token("Upgrade", "websocket")
+            && has_token("Connection", "upgrade")
+    }
+}
+
+fn write_response(stream: &mut TcpStream, (status, body): (u16, Value)) -> io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::server::{Options, Server};
+    use crate::state::example_state;
+    use crate::supervisor::{Backoff, Supervisor};
+    use std::time::Instant;
+
+    fn api() -> Api {
+        let api = Api::new();
+        api.publish(7, example_state());
+        api
+    }
+
+    #[test]
+    fn routes() {
+        let api = api();
+        let (status, body) = api.handle("GET", "/state");
+        assert_eq!(status, 200);
+        assert_eq!(body["message_number"], 7);
+        assert!(body["received_at"].as_u64().unwrap() > 0);
+        assert_eq!(body["state"]["round"], 5);
+
+        let (_, body) = api.handle("GET", "/state/actors");
+        assert_eq!(body["actors"].as_array().unwrap().len(), 9);
+
+        let (_, body) = api.handle("GET", "/state/elements");
+        assert_eq!(body["elements"]["light"], "Strong");
+        assert_eq!(body["elements"]["fire"], "Inert");
+
+        let (_, body) = api.handle("GET", "/state/modifiers?pretty");
+        assert_eq!(body["attack_modifiers"].as_array().unwrap().len(), 19);
+        assert_eq!(body["attack_modifiers_discard"][0], "Plus1");
+
+        let (_, body) = api.handle("GET", "/state/modifiers/odds?attack=2&mode=advantage&hp=3");
+        assert_eq!(body["odds"]["base_attack"], 2);
+        assert_eq!(body["odds"]["mode"], "Advantage");
+        assert!(body["kill_chance"].as_f64().unwrap() > 0.0);
+        let (_, body) = api.handle("GET", "/state/modifiers/odds?attack=%2D1&mode=%61dvantage");
+        assert_eq!(body["odds"]["base_attack"], -1);
+        assert_eq!(body["odds"]["mode"], "Advantage");
+
+        let (_, body) = api.handle("GET", "/state/scenario");
+        assert_eq!(body["scenario"]["number"], 5);
+        assert_eq!(body["level"]["trap_damage"], 3);
+        assert_eq!(
+            body["description"],
+            "Scenario 5: A Descent into Darkness, level 1, traps 3 damage, gold x2"
+        );
+
+        let (_, body) = api.handle("GET", "/state/level");
+        assert_eq!(body["recommendation"]["recommended"]["level"], 1);
+        assert_eq!(body["recommendation"]["deviates"], false);
+        let (_, body) = api.handle("GET", "/state/level?difficulty=hard");
+        assert_eq!(body["recommendation"]["recommended"]["level"], 2);
+        assert_eq!(body["recommendation"]["recommended"]["gold"], 3);
+        assert_eq!(body["recommendation"]["deviates"], true);
+
+        let (status, body) = api.handle("GET", "/state/decks/14");
+        assert_eq!(status, 200);
+        assert_eq!(body["deck"]["shown_ability"], 117);
+        assert_eq!(body["shown_card"]["number"], 117);
+        assert!(body["shown_card"]["initiative"].is_number());
+        assert_eq!(body["message_number"], 7);
+    }
+
+    #[test]
+    fn query_params() {
+        let query = "name=Bandit+Guard%21&%6Eumber=%2&empty=";
+        assert_eq!(query_param(query, "name").unwrap(), "Bandit Guard!");
+        assert_eq!(query_param(query, "number").unwrap(), "%2");
+        assert_eq!(query_param(query, "empty").unwrap(), "");
+        assert_eq!(query_param(query, "other"), None);
+        assert_eq!(percent_decode("%E2%9C%93%zz"), "\u{2713}%zz");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Api::new().handle("GET", "/state").0, 503);
+        let api = api();
+        assert_eq!(api.handle("GET", "/state/decks/3").0, 404);
+        assert_eq!(api.handle("GET", "/state/decks/x").0, 404);
+        assert_eq!(api.handle("GET", "/other").0, 404);
+        assert_eq!(api.handle("GET", "/state/level?difficulty=brutal").0, 400);
+        assert_eq!(api.handle("GET", "/state/modifiers/odds").0, 400);
+        assert_eq!(
+            api.handle("GET", "/state/modifiers/odds?attack=2&mode=lucky")
+                .0,
+            400
+        );
+        assert_eq!(api.handle("POST", "/state").0, 405);
+    }
+
+    #[test]
+    fn websocket() {
+        let api = api();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = api.clone();
+        std::thread::spawn(move || server.serve_listener(listener));
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", addr)).unwrap();
+        let mut next = || -> Value {
+            let message = socket.read().unwrap();
+            serde_json::from_str(message.to_text().unwrap()).unwrap()
+        };
+        let body = next();
+        assert_eq!(body["message_number"], 7);
+        assert_eq!(body["state"]["round"], 5);
+
+        let mut state = api.latest().unwrap().state;
+        state.round = 6;
+        api.publish(8, state);
+        let body = next();
+        assert_eq!(body["message_number"], 8);
+        assert_eq!(body["state"]["round"], 6);
+
+        let ping = tungstenite::Bytes::from_static(b"ping");
+        socket.send(Message::Ping(ping.clone())).unwrap();
+        assert_eq!(socket.read().unwrap(), Message::Pong(ping));
+
+        socket.close(None).unwrap();
+        loop {
+            match socket.read() {
+                Ok(message) => assert!(message.is_close()),
+                Err(tungstenite::Error::ConnectionClosed) => break,
+                Err(err) => panic!("{}", err),
+            }
+        }
+        // The connection's subscription goes away once the server has closed it too.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !api.subscribers.lock().unwrap().is_empty() {
+            assert!(Instant::now() < deadline, "the server kept the connection");
+            api.publish(9, example_state());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn http() {
+        let api = api();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || api.serve_listener(listener));
+
+        let request = |request: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = request("GET /state/scenario HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["scenario"]["number"], 5);
+
+        let response = request("GET /other HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let response = request("GET /ws HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        let key = "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n";
+        let response = request(&format!("GET /ws HTTP/1.1\r\n{}\r\n", key));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        let response = request(&format!(
+            "GET /ws HTTP/1.1\r\nUpgrade: websocket\r\nConnection: keep-alive\r\n{}\r\n",
+            key
+        ));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /ws HTTP/1.1\r\nUpgrade: WebSocket\r\nConnection: keep-alive, Upgrade\r\n{}\r\n",
+            key
+        )
+        .unwrap();
+        let mut response = [0; 34];
+        stream.read_exact(&mut response).unwrap();
+        assert_eq!(&response, b"HTTP/1.1 101 Switching Protocols\r\n");
+        let response = request("nonsense\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn commands() {
+        let server = Arc::new(Server::new(example_state(), Options::default()).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        {
+            let server = server.clone();
+            std::thread::spawn(move || server.serve(listener));
+        }
+
+        let supervisor = Supervisor::new(addr.to_string(), Backoff::default());
+        let api = Api::with_commands(supervisor.commands());
+        assert_eq!(api.handle("POST", "/round/end").0, 503);
+        let result = supervisor.run(|_| {
+            let (status, body) = api.handle("POST", "/round/end-turn?player=Brute");
+            assert_eq!((status, body["message_number"].as_i64()), (200, Some(1)));
+            assert_eq!(api.handle("POST", "/round/end-turn?player=Brute").0, 409);
+            assert_eq!(
+                api.handle("POST", "/round/end-turn?monster=19&number=2").0,
+                404
+            );
+            assert_eq!(api.handle("POST", "/round/end-turn?player=Wizard").0, 400);
+            assert_eq!(api.handle("POST", "/round/start").0, 409);
+            assert_eq!(api.handle("POST", "/round/end").0, 200);
+            assert_eq!(api.handle("POST", "/round/other").0, 404);
+            Err("done".into())
+        });
+        assert_eq!(result.unwrap_err().to_string(), "done");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.message_number() < 2 {
+            assert!(Instant::now() < deadline, "the commands never arrived");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(server.state().round, 6);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod abilities;
+pub mod monsters;
+pub mod scenarios;
@@ -0,0 +1,178 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::{AbilityDeck, Monster};
+
+/// One card of a monster ability deck.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct AbilityCard {
+    /// The number the helper refers to the card by, unique across all decks.
+    pub number: i32,
+    pub initiative: i32,
+    /// Whether the deck is shuffled at the end of a round in which this card was drawn.
+    #[serde(default)]
+    pub shuffle: bool,
+    /// What the monsters do, in order, e.g. "Move +1" and "Attack -1".
+    pub actions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct DeckInfo {
+    /// The id of the deck in [`AbilityDeck::id`].
+    pub id: i32,
+    pub name: String,
+    pub cards: Vec<AbilityCard>,
+}
+
+impl DeckInfo {
+    pub fn card(&self, number: i32) -> Option<&AbilityCard> {
+        self.cards.iter().find(|card| card.number == number)
+    }
+}
+
+impl AbilityCard {
+    /// Describes the card as played by `name`, e.g. "Bandit Guard, initiative 15: Move +1,
+    /// Attack -1".
+    pub fn describe(&self, name: &str) -> String {
+        format!(
+            "{}, initiative {}: {}",
+            name,
+            self.initiative,
+            self.actions.join(", ")
+        )
+    }
+}
+
+/// The cards of the monster ability decks, keyed by [`AbilityDeck::id`].
+#[derive(Clone, Debug, Default)]
+pub struct AbilityCatalogue {
+    decks: Vec<DeckInfo>,
+}
+
+static INSTALLED: OnceLock<AbilityCatalogue> = OnceLock::new();
+
+impl AbilityCatalogue {
+    /// Sample data bundled with this crate, from `src/catalogue/abilities.json`. It only covers
+    /// the decks of the monsters in scenario 5, so a complete catalogue has to be
+    /// [installed](Self::install) to play other scenarios.
+    pub fn sample() -> &'static AbilityCatalogue {
+        static SAMPLE: OnceLock<AbilityCatalogue> = OnceLock::new();
+        SAMPLE.get_or_init(|| {
+            AbilityCatalogue::from_json(include_str!("abilities.json"))
+                .expect("the sample ability catalogue is valid")
+        })
+    }
+
+    /// Makes this the catalogue [`current`](Self::current) returns. It can only be installed
+    /// once, so the catalogue is given back if one already was.
+    pub fn install(self) -> Result<(), AbilityCatalogue> {
+        INSTALLED.set(self)
+    }
+
+    /// The [installed](Self::install) catalogue, or the [sample](Self::sample) if there is
+    /// none.
+    pub fn current() -> &'static AbilityCatalogue {
+        INSTALLED.get().unwrap_or_else(AbilityCatalogue::sample)
+    }
+
+    /// Reads a catalogue in the same format as the sample.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        Ok(AbilityCatalogue {
+            decks: serde_json::from_str(json)?,
+        })
+    }
+
+    pub fn deck(&self, id: i32) -> Option<&DeckInfo> {
+        self.decks.iter().find(|deck| deck.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DeckInfo> {
+        self.decks.iter()
+    }
+}
+
+impl AbilityDeck {
+    /// Looks the deck up in the [current](AbilityCatalogue::current) catalogue.
+    pub fn info(&self) -> Option<&'static DeckInfo> {
+        AbilityCatalogue::current().deck(self.id)
+    }
+
+    /// The card that is face up, if any.
+    pub fn shown_card(&self) -> Option<&'static AbilityCard> {
+        self.info()?.card(self.shown_ability?.value)
+    }
+}
+
+impl Monster {
+    /// The monster's ability deck in the [current](AbilityCatalogue::current) catalogue.
+    pub fn deck(&self) -> Option<&'static DeckInfo> {
+        AbilityCatalogue::current().deck(self.info()?.deck)
+    }
+
+    /// The ability card the monster plays this round, if one has been drawn.
+    pub fn ability_card(&self) -> Option<&'static AbilityCard> {
+        self.deck()?.card(self.ability.value)
+    }
+
+    /// The monster's initiative this round, if an ability card has been drawn for it.
+    pub fn initiative(&self) -> Option<i32> {
+        self.ability_card().map(|card| card.initiative)
+    }
+
+    /// Describes the ability card the monster plays this round, e.g. "Bandit Guard, initiative
+    /// 15: Move +1, Attack -1".
+    pub fn describe_ability(&self) -> Option<String> {
+        Some(self.ability_card()?.describe(&self.info()?.name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::catalogue::monsters::MonsterCatalogue;
+    use crate::state::{example_state, Actor};
+
+    #[test]
+    fn sample() {
+        for deck in AbilityCatalogue::sample().iter() {
+            assert_eq!(deck.cards.len(), 8, "{}", deck.name);
+            for (i, card) in deck.cards.iter().enumerate() {
+                assert_eq!(card.number, deck.id * 8 + i as i32);
+            }
+        }
+        for monster in MonsterCatalogue::sample().iter() {
+            assert!(AbilityCatalogue::sample().deck(monster.deck).is_some());
+        }
+    }
+
+    #[test]
+    fn fixture() {
+        let state = example_state();
+        for deck in &state.ability_decks {
+            let info = deck.info().unwrap();
+            for number in deck.abilities.iter().chain(&deck.abilities_discard) {
+                assert!(info.card(*number).is_some(), "{} {}", deck.id, number);
+            }
+        }
+        assert_eq!(state.ability_decks[0].shown_card().unwrap().number, 39);
+        assert!(state.ability_decks[1].shown_card().is_none());
+
+        let monsters: Vec<_> = state
+            .actors
+            .iter()
+            .filter_map(|actor| match actor {
+                Actor::Monster(monster) => Some(monster),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(monsters[0].initiative(), Some(10));
+        assert_eq!(
+            monsters[0].describe_ability().unwrap(),
+            "Cultist, initiative 10: Move +1, Attack -1"
+        );
+        assert_eq!(monsters[1].ability_card().unwrap().number, 117);
+        // No card has been drawn for monsters that aren't on the board yet.
+        assert_eq!(monsters[2].initiative(), None);
+    }
+}
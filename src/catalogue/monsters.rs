@@ -0,0 +1,172 @@
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::{Condition, Monster, MonsterType};
+
+/// A monster's stats at one level, for either its normal or its elite standees.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub health: i32,
+    #[serde(rename = "move")]
+    pub movement: i32,
+    pub attack: i32,
+    /// Zero for melee attacks.
+    #[serde(default)]
+    pub range: i32,
+    #[serde(default)]
+    pub shield: i32,
+    #[serde(default)]
+    pub retaliate: i32,
+    /// Any other innate abilities, e.g. "Flying" or "Target 2".
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LevelStats {
+    pub normal: Stats,
+    pub elite: Stats,
+}
+
+/// Everything the catalogue knows about a monster.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MonsterInfo {
+    pub id: i32,
+    /// The id of the monster's ability deck, see
+    /// [`AbilityCatalogue`](super::abilities::AbilityCatalogue).
+    pub deck: i32,
+    pub name: String,
+    /// Conditions that can't be applied to the monster's standees.
+    #[serde(default)]
+    pub immunities: Vec<Condition>,
+    /// The stats at each monster level, starting from zero.
+    pub levels: Vec<LevelStats>,
+}
+
+impl MonsterInfo {
+    /// The stats of standees of type `tpe` at monster level `level`, or `None` for summons and
+    /// levels the catalogue doesn't have.
+    pub fn stats(&self, level: i32, tpe: MonsterType) -> Option<&Stats> {
+        let stats = self.levels.get(usize::try_from(level).ok()?)?;
+        match tpe {
+            MonsterType::Normal => Some(&stats.normal),
+            MonsterType::Elite => Some(&stats.elite),
+            MonsterType::Summon => None,
+        }
+    }
+}
+
+/// Monster names and stats, keyed by [`Monster::id`].
+#[derive(Clone, Debug, Default)]
+pub struct MonsterCatalogue {
+    monsters: Vec<MonsterInfo>,
+}
+
+static INSTALLED: OnceLock<MonsterCatalogue> = OnceLock::new();
+
+impl MonsterCatalogue {
+    /// Sample data bundled with this crate, from `src/catalogue/monsters.json`. It only covers
+    /// the monsters of scenario 5, so a complete catalogue has to be [installed](Self::install)
+    /// to play other scenarios.
+    pub fn sample() -> &'static MonsterCatalogue {
+        static SAMPLE: OnceLock<MonsterCatalogue> = OnceLock::new();
+        SAMPLE.get_or_init(|| {
+            MonsterCatalogue::from_json(include_str!("monsters.json"))
+                .expect("the sample monster catalogue is valid")
+        })
+    }
+
+    /// Makes this the catalogue [`current`](Self::current) returns. It can only be installed
+    /// once, so the catalogue is given back if one already was.
+    pub fn install(self) -> Result<(), MonsterCatalogue> {
+        INSTALLED.set(self)
+    }
+
+    /// The [installed](Self::install) catalogue, or the [sample](Self::sample) if there is
+    /// none.
+    pub fn current() -> &'static MonsterCatalogue {
+        INSTALLED.get().unwrap_or_else(MonsterCatalogue::sample)
+    }
+
+    /// Reads a catalogue in the same format as the sample.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        Ok(MonsterCatalogue {
+            monsters: serde_json::from_str(json)?,
+        })
+    }
+
+    pub fn get(&self, id: i32) -> Option<&MonsterInfo> {
+        self.monsters.iter().find(|monster| monster.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MonsterInfo> {
+        self.monsters.iter()
+    }
+}
+
+impl Monster {
+    /// Looks the monster up in the [current](MonsterCatalogue::current) catalogue.
+    pub fn info(&self) -> Option<&'static MonsterInfo> {
+        MonsterCatalogue::current().get(self.id)
+    }
+
+    /// The stats of this monster's standees of type `tpe`, at the monster's level.
+    pub fn stats(&self, tpe: MonsterType) -> Option<&'static Stats> {
+        self.info()?.stats(self.level, tpe)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{example_state, Actor};
+
+    #[test]
+    fn sample() {
+        for monster in MonsterCatalogue::sample().iter() {
+            assert_eq!(monster.levels.len(), 8, "{}", monster.name);
+        }
+        let cultist = MonsterCatalogue::sample().get(7).unwrap();
+        assert_eq!(cultist.name, "Cultist");
+        assert_eq!(cultist.stats(1, MonsterType::Normal).unwrap().health, 5);
+        assert!(cultist.stats(1, MonsterType::Summon).is_none());
+        assert!(cultist.stats(8, MonsterType::Normal).is_none());
+        assert!(cultist.stats(-1, MonsterType::Normal).is_none());
+        assert!(MonsterCatalogue::sample().get(-3).is_none());
+    }
+
+    #[test]
+    fn fixture() {
+        let state = example_state();
+        for actor in &state.actors {
+            if let Actor::Monster(monster) = actor {
+                assert!(monster.info().is_some(), "monster {}", monster.id);
+                for instance in &monster.instances {
+                    let stats = monster.stats(instance.tpe).unwrap();
+                    assert_eq!(stats.health, instance.hp_max);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_json() {
+        let catalogue = MonsterCatalogue::from_json(
+            r#"[{ "id": 1, "deck": 0, "name": "Test", "immunities": ["Poisoned"], "levels": [{
+                "normal": { "health": 3, "move": 1, "attack": 1, "range": 2 },
+                "elite": { "health": 5, "move": 1, "attack": 2, "retaliate": 1 }
+            }] }]"#,
+        )
+        .unwrap();
+        let monster = catalogue.get(1).unwrap();
+        assert_eq!(monster.immunities, [Condition::Poisoned]);
+        assert_eq!(monster.stats(0, MonsterType::Normal).unwrap().range, 2);
+        assert_eq!(monster.stats(0, MonsterType::Elite).unwrap().retaliate, 1);
+        assert!(MonsterCatalogue::from_json(
+            r#"[{ "id": 1, "deck": 0, "name": "Test", "immunities": ["Sleepy"], "levels": [] }]"#
+        )
+        .is_err());
+    }
+}
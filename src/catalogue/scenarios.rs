@@ -0,0 +1,181 @@
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::State;
+
+/// Everything the catalogue knows about a scenario.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ScenarioInfo {
+    /// The number in [`State::scenario_number`].
+    pub number: i32,
+    pub name: String,
+    pub goal: String,
+    /// The names of the monster groups that appear in the scenario.
+    #[serde(default)]
+    pub monsters: Vec<String>,
+    /// Rules that only apply to this scenario, e.g. how doors open or when reinforcements
+    /// arrive.
+    #[serde(default)]
+    pub special_rules: Vec<String>,
+    /// What the scenario's treasure tiles hold.
+    #[serde(default)]
+    pub treasures: Vec<String>,
+    /// The scenarios completing this one unlocks.
+    #[serde(default)]
+    pub links: Vec<i32>,
+}
+
+/// The values that follow from a scenario level.
+#[derive(Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LevelInfo {
+    pub level: i32,
+    pub monster_level: i32,
+    /// How much gold each money token is worth.
+    pub gold: i32,
+    pub trap_damage: i32,
+    /// The experience each character gains for completing the scenario.
+    pub bonus_experience: i32,
+}
+
+impl LevelInfo {
+    /// The values for scenario level `level`, or `None` if it isn't between 0 and 7.
+    pub fn new(level: i32) -> Option<Self> {
+        const GOLD: [i32; 8] = [2, 2, 3, 3, 4, 4, 5, 6];
+        let gold = *GOLD.get(usize::try_from(level).ok()?)?;
+        Some(LevelInfo {
+            level,
+            monster_level: level,
+            gold,
+            trap_damage: 2 + level,
+            bonus_experience: 4 + 2 * level,
+        })
+    }
+}
+
+/// Scenario names, goals and rules, keyed by [`State::scenario_number`].
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioCatalogue {
+    scenarios: Vec<ScenarioInfo>,
+}
+
+static INSTALLED: OnceLock<ScenarioCatalogue> = OnceLock::new();
+
+impl ScenarioCatalogue {
+    /// Sample data bundled with this crate, from `src/catalogue/scenarios.json`. It only has
+    /// the names, goals, monsters and links of scenarios 1 to 5, so a complete catalogue has to
+    /// be [installed](Self::install) for their special rules, treasures and the other scenarios.
+    pub fn sample() -> &'static ScenarioCatalogue {
+        static SAMPLE: OnceLock<ScenarioCatalogue> = OnceLock::new();
+        SAMPLE.get_or_init(|| {
+            ScenarioCatalogue::from_json(include_str!("scenarios.json"))
+                .expect("the sample scenario catalogue is valid")
+        })
+    }
+
+    /// Makes this the catalogue [`current`](Self::current) returns. It can only be installed
+    /// once, so the catalogue is given back if one already was.
+    pub fn install(self) -> Result<(), ScenarioCatalogue> {
+        INSTALLED.set(self)
+    }
+
+    /// The [installed](Self::install) catalogue, or the [sample](Self::sample) if there is
+    /// none.
+    pub fn current() -> &'static ScenarioCatalogue {
+        INSTALLED.get().unwrap_or_else(ScenarioCatalogue::sample)
+    }
+
+    /// Reads a catalogue in the same format as the sample.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        Ok(ScenarioCatalogue {
+            scenarios: serde_json::from_str(json)?,
+        })
+    }
+
+    pub fn get(&self, number: i32) -> Option<&ScenarioInfo> {
+        self.scenarios
+            .iter()
+            .find(|scenario| scenario.number == number)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScenarioInfo> {
+        self.scenarios.iter()
+    }
+}
+
+impl State {
+    /// Looks the scenario being played up in the [current](ScenarioCatalogue::current)
+    /// catalogue.
+    pub fn scenario(&self) -> Option<&'static ScenarioInfo> {
+        ScenarioCatalogue::current().get(self.scenario_number)
+    }
+
+    /// The values that follow from the scenario level being played.
+    pub fn level_info(&self) -> Option<LevelInfo> {
+        LevelInfo::new(self.scenario_level)
+    }
+
+    /// Describes the scenario being played, e.g. "Scenario 5: A Descent into Darkness, level 1,
+    /// traps 3 damage, gold x2".
+    pub fn describe_scenario(&self) -> String {
+        let mut description = format!("Scenario {}", self.scenario_number);
+        if let Some(scenario) = self.scenario() {
+            description += &format!(": {}", scenario.name);
+        }
+        description += &format!(", level {}", self.scenario_level);
+        if let Some(level) = self.level_info() {
+            description += &format!(", traps {} damage, gold x{}", level.trap_damage, level.gold);
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::example_state;
+
+    #[test]
+    fn levels() {
+        let levels: Vec<_> = (0..8).map(|level| LevelInfo::new(level).unwrap()).collect();
+        assert_eq!(levels[0].trap_damage, 2);
+        assert_eq!(levels[0].bonus_experience, 4);
+        assert_eq!(levels[7].gold, 6);
+        assert_eq!(levels[7].trap_damage, 9);
+        assert_eq!(levels[7].bonus_experience, 18);
+        assert_eq!(levels[3].monster_level, 3);
+        assert!(LevelInfo::new(8).is_none());
+        assert!(LevelInfo::new(-1).is_none());
+    }
+
+    #[test]
+    fn fixture() {
+        let mut state = example_state();
+        assert_eq!(state.scenario().unwrap().name, "A Descent into Darkness");
+        assert_eq!(
+            state.describe_scenario(),
+            "Scenario 5: A Descent into Darkness, level 1, traps 3 damage, gold x2"
+        );
+        state.scenario_number = 95;
+        state.scenario_level = 9;
+        assert_eq!(state.describe_scenario(), "Scenario 95, level 9");
+    }
+    #[test]
+    fn from_json() {
+        let catalogue = ScenarioCatalogue::from_json(
+            r#"[{ "number": 96, "name": "Test", "goal": "Loot the treasure tile",
+                "special_rules": ["The door opens at the end of round 3"],
+                "treasures": ["25 gold"] }]"#,
+        )
+        .unwrap();
+        let scenario = catalogue.get(96).unwrap();
+        assert_eq!(
+            scenario.special_rules,
+            ["The door opens at the end of round 3"]
+        );
+        assert_eq!(scenario.treasures, ["25 gold"]);
+        assert!(scenario.monsters.is_empty());
+        assert!(ScenarioCatalogue::from_json(r#"[{ "number": 96 }]"#).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::fmt;
+use std::io;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+use crate::diff::Target;
+use crate::frame::Frame;
+use crate::modifiers::DrawMode;
+use crate::round::RoundError;
+use crate::state::{
+    self, Actor, CharacterClass, Condition, Element, ElementState, MonsterInstance, Player, State,
+};
+
+/// A change to make to the game, as if it was made on a helper device.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    SetInitiative {
+        character_class: CharacterClass,
+        initiative: i32,
+    },
+    /// Takes `damage` hit points from a monster standee, down to zero.
+    DamageInstance {
+        monster_id: i32,
+        number: i32,
+        damage: i32,
+    },
+    AddCondition {
+        target: Target,
+        condition: Condition,
+    },
+    InfuseElement(Element),
+    /// Draws the top attack modifier, as described in
+    /// [`ModifierDeck::draw`](crate::modifiers::ModifierDeck::draw).
+    DrawModifier,
+    AdvanceRound,
+    /// Draws the monster ability cards, see [`State::start_round`].
+    StartRound,
+    /// Ends the turn of a figure, see [`State::end_turn`].
+    EndTurn(Target),
+    /// Ends the round, see [`State::end_round`].
+    EndRound,
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    /// The player, summon or monster standee the command refers to isn't in the state.
+    UnknownTarget(Target),
+    EmptyModifierDeck,
+    /// No state has been received over the current connection yet.
+    NotConnected,
+    Round(RoundError),
+    State(state::Error),
+    Io(io::Error),
+}
+
+impl Command {
+    /// Makes the change to `state`.
+    pub fn apply(&self, state: &mut State) -> Result<(), CommandError> {
+        match *self {
+            Command::SetInitiative {
+                character_class,
+                initiative,
+            } => {
+                player_mut(state, character_class)
+                    .ok_or(CommandError::UnknownTarget(Target::Player(character_class)))?
+                    .initiative = initiative;
+            }
+            Command::DamageInstance {
+                monster_id,
+                number,
+                damage,
+            } => {
+                let target = Target::MonsterInstance { monster_id, number };
+                let instance =
+                    instance_mut(state, target).ok_or(CommandError::UnknownTarget(target))?;
+                instance.hp = (instance.hp - damage).max(0);
+            }
+            Command::AddCondition { target, condition } => {
+                let conditions = match target {
+                    Target::Player(class) => player_mut(state, class).map(|p| &mut p.conditions),
+                    _ => instance_mut(state, target).map(|instance| &mut instance.conditions),
+                }
+                .ok_or(CommandError::UnknownTarget(target))?;
+                if !conditions.contains(&condition) {
+                    conditions.push(condition);
+                }
+            }
+            Command::InfuseElement(element) => *state.element_mut(element) = ElementState::Strong,
+            Command::DrawModifier => {
+                state
+                    .modifier_deck()
+                    .draw(DrawMode::Normal, &mut rand::rng())
+                    .ok_or(CommandError::EmptyModifierDeck)?;
+            }
+            Command::AdvanceRound => state.round += 1,
+            Command::StartRound => state
+                .start_round(&mut rand::rng())
+                .map_err(CommandError::Round)?,
+            Command::EndTurn(target) => state.end_turn(target).map_err(CommandError::Round)?,
+            Command::EndRound => state.end_round(&mut rand::rng()),
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn player_mut(state: &mut State, class: CharacterClass) -> Option<&mut Player> {
+    state.actors.iter_mut().find_map(|actor| match actor {
+        Actor::Player(player) if player.character_class == class => Some(player),
+        _ => None,
+    })
+}
+
+pub(crate) fn instance_mut(state: &mut State, target: Target) -> Option<&mut MonsterInstance> {
+    let (instances, number) = match target {
+        Target::Player(_) => return None,
+        Target::Summon { owner, number } => (&mut player_mut(state, owner)?.instances, number),
+        Target::MonsterInstance { monster_id, number } => {
+            let monster = state.actors.iter_mut().find_map(|actor| match actor {
+                Actor::Monster(monster) if monster.id == monster_id => Some(monster),
+                _ => None,
+            })?;
+            (&mut monster.instances, number)
+        }
+    };
+    instances
+        .iter_mut()
+        .find(|instance| instance.number == number)
+}
+
+/// Sends [`Command`]s to the helper server a [`Supervisor`](crate::supervisor::Supervisor) is
+/// connected to.
+///
+/// Commands are applied to the latest state received and the result is sent back over the same
+/// connection, numbered one past that state the way helper clients number their changes.
+#[derive(Clone, Default)]
+pub struct CommandSender {
+    connection: Arc<Mutex<Connection>>,
+}
+
+#[derive(Default)]
+struct Connection {
+    stream: Option<TcpStream>,
+    latest: Option<Frame>,
+    /// The round and [`State::ended_turns`] of the last state a command was applied to, as the
+    /// helper doesn't send them.
+    ended_turns: (i32, Vec<Target>),
+}
+
+impl CommandSender {
+    pub fn new() -> Self {
+        CommandSender::default()
+    }
+
+    pub(crate) fn connected(&self, stream: TcpStream) {
+        *self.connection.lock().unwrap() = Connection {
+            stream: Some(stream),
+            ..Connection::default()
+        };
+    }
+
+    pub(crate) fn received(&self, frame: &Frame) {
+        if frame.message_number.is_some() {
+            self.connection.lock().unwrap().latest = Some(frame.clone());
+        }
+    }
+
+    pub(crate) fn disconnected(&self) {
+        *self.connection.lock().unwrap() = Connection::default();
+    }
+
+    /// Applies `command` to the latest state and sends the result, returning the message
+    /// number it was sent with.
+    pub fn send(&self, command: &Command) -> Result<i32, CommandError> {
+        let mut connection = self.connection.lock().unwrap();
+        let Connection {
+            stream,
+            latest,
+            ended_turns,
+        } = &mut *connection;
+        let (stream, latest) = match (stream, latest) {
+            (Some(stream), Some(latest)) => (stream, latest),
+            _ => return Err(CommandError::NotConnected),
+        };
+        let mut state = latest.state().map_err(CommandError::State)?;
+        if state.round == ended_turns.0 {
+            state.ended_turns = ended_turns.1.clone();
+        }
+        command.apply(&mut state)?;
+        let frame = Frame {
+            header: latest.header.clone(),
+            message_number: latest.message_number.map(|n| n + 1),
+            data: state::to_bytes(&state).map_err(CommandError::State)?,
+        };
+        frame.write_to(stream).map_err(CommandError::Io)?;
+        let message_number = frame.message_number.unwrap_or_default();
+        // Further commands build on this one, even before the server echoes it back.
+        *latest = frame;
+        *ended_turns = (state.round, state.ended_turns);
+        Ok(message_number)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::UnknownTarget(target) => write!(f, "{} is not in the game", target),
+            CommandError::EmptyModifierDeck => write!(f, "the attack modifier deck is empty"),
+            CommandError::NotConnected => write!(f, "not connected to a helper server"),
+            CommandError::Round(err) => write!(f, "{}", err),
+            CommandError::State(err) => write!(f, "{}", err),
+            CommandError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::Round(err) => Some(err),
+            CommandError::State(err) => Some(err),
+            CommandError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::server::{Options, Server};
+    use crate::state::example_state;
+    use crate::supervisor::{Backoff, Supervisor};
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn apply() {
+        let mut state = example_state();
+        let commands = [
+            Command::SetInitiative {
+                character_class: CharacterClass::Brute,
+                initiative: 41,
+            },
+            Command::DamageInstance {
+                monster_id: 7,
+                number: 2,
+                damage: 3,
+            },
+            Command::DamageInstance {
+                monster_id: 7,
+                number: 3,
+                damage: 9,
+            },
+            Command::AddCondition {
+                target: Target::MonsterInstance {
+                    monster_id: 7,
+                    number: 2,
+                },
+                condition: Condition::Poisoned,
+            },
+            Command::AddCondition {
+                target: Target::Player(CharacterClass::Scoundrel),
+                condition: Condition::Invisible,
+            },
+            Command::InfuseElement(Element::Ice),
+            Command::DrawModifier,
+            Command::DrawModifier,
+            Command::AdvanceRound,
+        ];
+        for command in &commands {
+            command.apply(&mut state).unwrap();
+        }
+
+        let mut expected = example_state();
+        expected.round = 6;
+        expected.ice = ElementState::Strong;
+        let drawn: Vec<_> = expected.attack_modifiers.drain(..2).collect();
+        expected.attack_modifiers_discard.extend(drawn);
+        expected.needs_shuffle = true;
+        if let Actor::Player(brute) = &mut expected.actors[2] {
+            brute.initiative = 41;
+        }
+        if let Actor::Player(scoundrel) = &mut expected.actors[1] {
+            scoundrel.conditions.push(Condition::Invisible);
+        }
+        if let Actor::Monster(monster) = &mut expected.actors[3] {
+            monster.instances[1].hp = 2;
+            monster.instances[1].conditions.push(Condition::Poisoned);
+            monster.instances[2].hp = 0;
+        }
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn unknown_target() {
+        let mut state = example_state();
+        let command = Command::DamageInstance {
+            monster_id: 7,
+            number: 9,
+            damage: 1,
+        };
+        assert!(matches!(
+            command.apply(&mut state),
+            Err(CommandError::UnknownTarget(_))
+        ));
+        let command = Command::SetInitiative {
+            character_class: CharacterClass::Tinkerer,
+            initiative: 10,
+        };
+        assert!(matches!(
+            command.apply(&mut state),
+            Err(CommandError::UnknownTarget(_))
+        ));
+        assert_eq!(state, example_state());
+    }
+
+    #[test]
+    fn round() {
+        let mut state = example_state();
+        let end_turn = Command::EndTurn(Target::Player(CharacterClass::Brute));
+        end_turn.apply(&mut state).unwrap();
+        assert!(matches!(
+            end_turn.apply(&mut state),
+            Err(CommandError::Round(RoundError::TurnAlreadyEnded(_)))
+        ));
+        assert!(matches!(
+            Command::StartRound.apply(&mut state),
+            Err(CommandError::Round(RoundError::AlreadyDrawn))
+        ));
+        Command::EndRound.apply(&mut state).unwrap();
+        assert_eq!(state.round, 6);
+        Command::StartRound.apply(&mut state).unwrap();
+        assert!(!state.can_draw);
+    }
+
+    #[test]
+    fn send() {
+        let server = Arc::new(Server::new(example_state(), Options::default()).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        {
+            let server = server.clone();
+            std::thread::spawn(move || server.serve(listener));
+        }
+
+        let supervisor = Supervisor::new(addr.to_string(), Backoff::default());
+        let commands = supervisor.commands();
+        assert!(matches!(
+            commands.send(&Command::AdvanceRound),
+            Err(CommandError::NotConnected)
+        ));
+        let result = supervisor.run(|_| {
+            assert_eq!(commands.send(&Command::AdvanceRound)?, 1);
+            assert_eq!(commands.send(&Command::AdvanceRound)?, 2);
+            // The standees that ended their turn are remembered from one command to the next.
+            let end_turn = Command::EndTurn(Target::MonsterInstance {
+                monster_id: 19,
+                number: 10,
+            });
+            assert_eq!(commands.send(&end_turn)?, 3);
+            assert!(matches!(
+                commands.send(&end_turn),
+                Err(CommandError::Round(RoundError::TurnAlreadyEnded(_)))
+            ));
+            // Closing the connection with the server's replies unread could reset it before the
+            // server has read the commands, so they have to arrive first.
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while server.message_number() < 3 {
+                assert!(Instant::now() < deadline, "the commands never arrived");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Err("done".into())
+        });
+        assert_eq!(result.unwrap_err().to_string(), "done");
+        assert_eq!(server.state().round, 7);
+    }
+}
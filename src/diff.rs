@@ -0,0 +1,352 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::catalogue::abilities::AbilityCatalogue;
+use crate::catalogue::monsters::MonsterCatalogue;
+use crate::state::{
+    Ability, Actor, AttackModifier, CharacterClass, Condition, Element, ElementState,
+    MonsterInstance, State,
+};
+
+/// Who a [`StateChange`] happened to.
+#[derive(Serialize, Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
+pub enum Target {
+    /// The player playing the given class.
+    Player(CharacterClass),
+    /// One of the summons of the player playing `owner`.
+    Summon { owner: CharacterClass, number: i32 },
+    /// A standee of the monster with the given id.
+    MonsterInstance { monster_id: i32, number: i32 },
+}
+
+/// Something that happened between two consecutive states, as found by [`diff`].
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub enum StateChange {
+    RoundAdvanced {
+        from: i32,
+        to: i32,
+    },
+    ElementChanged {
+        element: Element,
+        from: ElementState,
+        to: ElementState,
+    },
+    MonsterInstanceDamaged {
+        monster_id: i32,
+        number: i32,
+        hp_before: i32,
+        hp_after: i32,
+    },
+    ConditionAdded {
+        target: Target,
+        condition: Condition,
+    },
+    ModifierDrawn {
+        modifier: AttackModifier,
+    },
+    AbilityCardRevealed {
+        deck_id: i32,
+        ability: Ability,
+    },
+    PlayerExhausted {
+        character_class: CharacterClass,
+    },
+}
+
+/// Lists what changed from `old` to `new`.
+///
+/// Players are matched up by their class, monsters by their id and standees by their number.
+/// Actors, standees and decks that only appear in `new` don't produce any changes themselves.
+pub fn diff(old: &State, new: &State) -> Vec<StateChange> {
+    let mut changes = Vec::new();
+    if new.round > old.round {
+        changes.push(StateChange::RoundAdvanced {
+            from: old.round,
+            to: new.round,
+        });
+    }
+    for &element in Element::ALL.iter() {
+        let (from, to) = (old.element(element), new.element(element));
+        if from != to {
+            changes.push(StateChange::ElementChanged { element, from, to });
+        }
+    }
+
+    // The deck is reshuffled at random, so compare its contents rather than its order.
+    let mut remaining = new.attack_modifiers.clone();
+    for &modifier in &old.attack_modifiers {
+        match remaining.iter().position(|&m| m == modifier) {
+            Some(i) => {
+                remaining.swap_remove(i);
+            }
+            None => changes.push(StateChange::ModifierDrawn { modifier }),
+        }
+    }
+
+    for deck in &new.ability_decks {
+        let shown = old
+            .ability_decks
+            .iter()
+            .find(|old| old.id == deck.id)
+            .and_then(|old| old.shown_ability);
+        if let Some(ability) = deck.shown_ability.filter(|&ability| Some(ability) != shown) {
+            changes.push(StateChange::AbilityCardRevealed {
+                deck_id: deck.id,
+                ability,
+            });
+        }
+    }
+
+    for actor in &new.actors {
+        match actor {
+            Actor::Player(player) => {
+                let old = old.actors.iter().find_map(|actor| match actor {
+                    Actor::Player(old) if old.character_class == player.character_class => {
+                        Some(old)
+                    }
+                    _ => None,
+                });
+                let old = match old {
+                    Some(old) => old,
+                    None => continue,
+                };
+                let owner = player.character_class;
+                conditions_added(
+                    &mut changes,
+                    Target::Player(owner),
+                    &old.conditions,
+                    &player.conditions,
+                );
+                for (old, summon) in instances(&old.instances, &player.instances) {
+                    let target = Target::Summon {
+                        owner,
+                        number: summon.number,
+                    };
+                    conditions_added(&mut changes, target, &old.conditions, &summon.conditions);
+                }
+                if player.exhausted && !old.exhausted {
+                    changes.push(StateChange::PlayerExhausted {
+                        character_class: owner,
+                    });
+                }
+            }
+            Actor::Monster(monster) => {
+                let old = old.actors.iter().find_map(|actor| match actor {
+                    Actor::Monster(old) if old.id == monster.id => Some(old),
+                    _ => None,
+                });
+                let old = match old {
+                    Some(old) => old,
+                    None => continue,
+                };
+                for (old, instance) in instances(&old.instances, &monster.instances) {
+                    if instance.hp < old.hp {
+                        changes.push(StateChange::MonsterInstanceDamaged {
+                            monster_id: monster.id,
+                            number: instance.number,
+                            hp_before: old.hp,
+                            hp_after: instance.hp,
+                        });
+                    }
+                    let target = Target::MonsterInstance {
+                        monster_id: monster.id,
+                        number: instance.number,
+                    };
+                    conditions_added(&mut changes, target, &old.conditions, &instance.conditions);
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Pairs up the instances in `new` with the ones in `old` that have the same number.
+fn instances<'a>(
+    old: &'a [MonsterInstance],
+    new: &'a [MonsterInstance],
+) -> impl Iterator<Item = (&'a MonsterInstance, &'a MonsterInstance)> {
+    new.iter().filter_map(move |instance| {
+        old.iter()
+            .find(|old| old.number == instance.number)
+            .map(|old| (old, instance))
+    })
+}
+
+fn conditions_added(
+    changes: &mut Vec<StateChange>,
+    target: Target,
+    old: &[Condition],
+    new: &[Condition],
+) {
+    for &condition in new.iter().filter(|condition| !old.contains(condition)) {
+        changes.push(StateChange::ConditionAdded { target, condition });
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Player(class) => write!(f, "{:?}", class),
+            Target::Summon { owner, number } => write!(f, "{:?}'s summon #{}", owner, number),
+            Target::MonsterInstance { monster_id, number } => {
+                match MonsterCatalogue::current().get(*monster_id) {
+                    Some(monster) => write!(f, "{} #{}", monster.name, number),
+                    None => write!(f, "monster {} #{}", monster_id, number),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateChange::RoundAdvanced { from, to } => {
+                write!(f, "round advanced from {} to {}", from, to)
+            }
+            StateChange::ElementChanged { element, from, to } => {
+                write!(f, "{:?} went from {:?} to {:?}", element, from, to)
+            }
+            StateChange::MonsterInstanceDamaged {
+                monster_id,
+                number,
+                hp_before,
+                hp_after,
+            } => write!(
+                f,
+                "{} took {} damage ({} -> {} hp)",
+                Target::MonsterInstance {
+                    monster_id: *monster_id,
+                    number: *number
+                },
+                hp_before - hp_after,
+                hp_before,
+                hp_after
+            ),
+            StateChange::ConditionAdded { target, condition } => {
+                write!(f, "{} is {:?}", target, condition)
+            }
+            StateChange::ModifierDrawn { modifier } => {
+                write!(f, "drew attack modifier {:?}", modifier)
+            }
+            StateChange::AbilityCardRevealed { deck_id, ability } => {
+                let deck = AbilityCatalogue::current().deck(*deck_id);
+                match deck.and_then(|deck| Some((deck, deck.card(ability.value)?))) {
+                    Some((deck, card)) => write!(f, "{}", card.describe(&deck.name)),
+                    None => write!(f, "deck {} revealed ability {}", deck_id, ability.value),
+                }
+            }
+            StateChange::PlayerExhausted { character_class } => {
+                write!(f, "{:?} is exhausted", character_class)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{example_state, Player};
+
+    fn player(state: &mut State, class: CharacterClass) -> &mut Player {
+        state
+            .actors
+            .iter_mut()
+            .find_map(|actor| match actor {
+                Actor::Player(player) if player.character_class == class => Some(player),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn monster(state: &mut State, id: i32) -> &mut Vec<MonsterInstance> {
+        state
+            .actors
+            .iter_mut()
+            .find_map(|actor| match actor {
+                Actor::Monster(monster) if monster.id == id => Some(&mut monster.instances),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(diff(&example_state(), &example_state()), []);
+    }
+
+    #[test]
+    fn changes() {
+        let old = example_state();
+        let mut new = example_state();
+        new.round = 6;
+        new.light = ElementState::Waning;
+        new.fire = ElementState::Strong;
+        let drawn = new.attack_modifiers.remove(1);
+        new.attack_modifiers_discard.push(drawn);
+        new.ability_decks[1].shown_ability = Some(Ability { value: 58 });
+        new.ability_decks[0].shown_ability = None;
+        player(&mut new, CharacterClass::Brute).exhausted = true;
+        player(&mut new, CharacterClass::Scoundrel)
+            .conditions
+            .push(Condition::Poisoned);
+        let instances = monster(&mut new, 7);
+        instances[1].hp = 2;
+        instances[1].conditions.push(Condition::Wounded);
+        instances[2].hp = 9;
+
+        assert_eq!(
+            diff(&old, &new),
+            [
+                StateChange::RoundAdvanced { from: 5, to: 6 },
+                StateChange::ElementChanged {
+                    element: Element::Fire,
+                    from: ElementState::Inert,
+                    to: ElementState::Strong,
+                },
+                StateChange::ElementChanged {
+                    element: Element::Light,
+                    from: ElementState::Strong,
+                    to: ElementState::Waning,
+                },
+                StateChange::ModifierDrawn {
+                    modifier: AttackModifier::Crit
+                },
+                StateChange::AbilityCardRevealed {
+                    deck_id: 7,
+                    ability: Ability { value: 58 },
+                },
+                StateChange::ConditionAdded {
+                    target: Target::Player(CharacterClass::Scoundrel),
+                    condition: Condition::Poisoned,
+                },
+                StateChange::PlayerExhausted {
+                    character_class: CharacterClass::Brute
+                },
+                StateChange::MonsterInstanceDamaged {
+                    monster_id: 7,
+                    number: 2,
+                    hp_before: 5,
+                    hp_after: 2,
+                },
+                StateChange::ConditionAdded {
+                    target: Target::MonsterInstance {
+                        monster_id: 7,
+                        number: 2
+                    },
+                    condition: Condition::Wounded,
+                },
+            ]
+        );
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes[7].to_string(),
+            "Cultist #2 took 3 damage (5 -> 2 hp)"
+        );
+        assert_eq!(
+            changes[4].to_string(),
+            "Flame Demon, initiative 46: Attack +0, Range +0, Fire: all adjacent enemies suffer 2 damage"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+
+use crate::state::{Element, ElementState, State};
+
+/// The state of every element, plus the elements infused during the current turn.
+///
+/// Infusions normally only take effect at the end of the turn that makes them, so an element
+/// can't be consumed by the same turn that infuses it. [`infuse_at_end_of_turn`] records such an
+/// infusion and [`end_of_turn`] applies it, while [`infuse`] makes an element Strong right away
+/// the way the helper's element buttons do.
+///
+/// [`infuse_at_end_of_turn`]: Elements::infuse_at_end_of_turn
+/// [`end_of_turn`]: Elements::end_of_turn
+/// [`infuse`]: Elements::infuse
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Elements {
+    states: [ElementState; 6],
+    infusing: Vec<Element>,
+}
+
+impl Element {
+    /// The lowercase name of the element, as used for the fields of a [`State`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Element::Fire => "fire",
+            Element::Ice => "ice",
+            Element::Air => "air",
+            Element::Earth => "earth",
+            Element::Light => "light",
+            Element::Dark => "dark",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Elements {
+    pub fn new() -> Self {
+        Elements::default()
+    }
+
+    pub fn get(&self, element: Element) -> ElementState {
+        self.states[element.index()]
+    }
+
+    pub fn set(&mut self, element: Element, state: ElementState) {
+        self.states[element.index()] = state;
+    }
+
+    /// Every element with its state, in the order the helper sends them.
+    pub fn iter(&self) -> impl Iterator<Item = (Element, ElementState)> + '_ {
+        Element::ALL
+            .iter()
+            .map(move |&element| (element, self.get(element)))
+    }
+
+    /// Makes `element` Strong right away.
+    pub fn infuse(&mut self, element: Element) {
+        self.set(element, ElementState::Strong);
+    }
+
+    /// Makes `element` Strong once the current turn ends.
+    pub fn infuse_at_end_of_turn(&mut self, element: Element) {
+        if !self.infusing.contains(&element) {
+            self.infusing.push(element);
+        }
+    }
+
+    /// The elements that will be infused at the end of the current turn.
+    pub fn infusing(&self) -> &[Element] {
+        &self.infusing
+    }
+
+    /// Consumes `element`, returning whether it was Strong or Waning and so could be consumed.
+    pub fn consume(&mut self, element: Element) -> bool {
+        let consumable = self.get(element) != ElementState::Inert;
+        self.set(element, ElementState::Inert);
+        consumable
+    }
+
+    /// Applies the infusions made during the turn that just ended.
+    pub fn end_of_turn(&mut self) {
+        for element in std::mem::take(&mut self.infusing) {
+            self.infuse(element);
+        }
+    }
+
+    /// Strong elements become Waning and Waning elements become Inert.
+    pub fn end_of_round_wane(&mut self) {
+        for state in &mut self.states {
+            *state = match state {
+                ElementState::Strong => ElementState::Waning,
+                ElementState::Waning | ElementState::Inert => ElementState::Inert,
+            };
+        }
+    }
+}
+
+impl State {
+    /// The state of every element. No infusions are pending in the result, as the helper
+    /// doesn't keep track of them.
+    pub fn elements(&self) -> Elements {
+        let mut elements = Elements::new();
+        for &element in Element::ALL.iter() {
+            elements.set(element, self.element(element));
+        }
+        elements
+    }
+
+    /// Sets the state of every element from `elements`, leaving out any pending infusions.
+    pub fn set_elements(&mut self, elements: &Elements) {
+        for (element, state) in elements.iter() {
+            *self.element_mut(element) = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::example_state;
+
+    #[test]
+    fn lifecycle() {
+        let mut state = example_state();
+        let mut elements = state.elements();
+        assert_eq!(elements.get(Element::Light), ElementState::Strong);
+        assert_eq!(elements.get(Element::Fire), ElementState::Inert);
+
+        elements.infuse_at_end_of_turn(Element::Fire);
+        assert_eq!(elements.infusing(), [Element::Fire]);
+        assert!(!elements.consume(Element::Fire));
+        elements.end_of_turn();
+        assert_eq!(elements.infusing(), []);
+        assert_eq!(elements.get(Element::Fire), ElementState::Strong);
+
+        elements.infuse(Element::Dark);
+        elements.end_of_round_wane();
+        assert_eq!(elements.get(Element::Fire), ElementState::Waning);
+        assert_eq!(elements.get(Element::Light), ElementState::Waning);
+        assert!(elements.consume(Element::Fire));
+        assert_eq!(elements.get(Element::Fire), ElementState::Inert);
+        elements.end_of_round_wane();
+        assert_eq!(elements.get(Element::Dark), ElementState::Inert);
+
+        elements.set(Element::Air, ElementState::Strong);
+        state.set_elements(&elements);
+        assert_eq!(state.air, ElementState::Strong);
+        assert_eq!(state.light, ElementState::Inert);
+        assert_eq!(state.elements(), elements);
+    }
+}
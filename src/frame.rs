@@ -0,0 +1,207 @@
+use std::io::{self, Read, Write};
+
+use crate::state::{self, State};
+
+/// A single message from a helper server.
+///
+/// On the wire a frame is a string prefixed by its length as a big endian `u16`, followed by
+/// the length of the payload as a varint. A non-empty payload starts with the message number
+/// as a big endian `i32`, and the rest of it is the encoded [`State`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Frame {
+    pub header: String,
+    pub message_number: Option<i32>,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// Decodes the state carried by the frame.
+    pub fn state(&self) -> Result<State, state::Error> {
+        state::from_bytes(&self.data)
+    }
+
+    /// Encodes the frame in the same format [`FrameReader`] reads.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        state::write_utf(&mut bytes, &self.header);
+        if bytes.len() > u16::MAX as usize {
+            return Err(invalid_data("frame header too long"));
+        }
+        let mut out = Vec::with_capacity(bytes.len() + self.data.len() + 11);
+        out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+        out.append(&mut bytes);
+        match self.message_number {
+            Some(message_number) => {
+                state::write_varint(&mut out, self.data.len() as i32 + 4);
+                out.extend_from_slice(&message_number.to_be_bytes());
+                out.extend_from_slice(&self.data);
+            }
+            None => state::write_varint(&mut out, 0),
+        }
+        writer.write_all(&out)
+    }
+}
+
+/// Reads [`Frame`]s from anything implementing `Read`, e.g. a `TcpStream`, a file or a byte
+/// slice.
+pub struct FrameReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(inner: R) -> Self {
+        FrameReader {
+            inner,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the next frame, or returns `None` if the input ended cleanly between two frames.
+    pub fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut len = [0u8; 2];
+        match self.inner.read(&mut len[..1])? {
+            0 => return Ok(None),
+            _ => self.inner.read_exact(&mut len[1..])?,
+        }
+        let header_len = u16::from_be_bytes(len) as usize;
+        self.buf.resize(header_len, 0);
+        self.inner.read_exact(&mut self.buf)?;
+        let header = state::read_utf(&self.buf).map_err(invalid_data)?;
+
+        let data_len = self.read_varint()?;
+        if data_len < 0 {
+            return Err(invalid_data(format!("negative frame length {}", data_len)));
+        }
+        // Don't trust the length for allocations, the buffer only grows as data arrives.
+        let mut data = Vec::new();
+        (&mut self.inner)
+            .take(data_len as u64)
+            .read_to_end(&mut data)?;
+        if data.len() < data_len as usize {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if data.is_empty() {
+            return Ok(Some(Frame {
+                header,
+                message_number: None,
+                data,
+            }));
+        }
+        if data.len() < 4 {
+            return Err(invalid_data("frame too short for a message number"));
+        }
+        let message_number = i32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        data.drain(..4);
+        Ok(Some(Frame {
+            header,
+            message_number: Some(message_number),
+            data,
+        }))
+    }
+
+    fn read_varint(&mut self) -> io::Result<i32> {
+        let mut buf = [0u8; 5];
+        for i in 0..buf.len() {
+            self.inner.read_exact(&mut buf[i..=i])?;
+            if let Some((_, value)) = state::read_varint(&buf[..=i]) {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("varint longer than five bytes"))
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame_bytes(header: &str, message_number: i32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        Frame {
+            header: header.to_string(),
+            message_number: Some(message_number),
+            data: data.to_vec(),
+        }
+        .write_to(&mut bytes)
+        .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn read_frames() {
+        let state = include_bytes!("example_state.bin");
+        let large = vec![0xAB; 4000];
+        let mut bytes = frame_bytes("state", 7, state);
+        bytes.extend(frame_bytes("state", 8, &large));
+        Frame {
+            header: String::new(),
+            message_number: None,
+            data: Vec::new(),
+        }
+        .write_to(&mut bytes)
+        .unwrap();
+        assert_eq!(&bytes[bytes.len() - 3..], &[0, 0, 0]);
+
+        let mut reader = FrameReader::new(&bytes[..]);
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.header, "state");
+        assert_eq!(frame.message_number, Some(7));
+        assert_eq!(frame.data, state.to_vec());
+        assert_eq!(frame.state().unwrap().round, 5);
+
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(8));
+        assert_eq!(frame.data, large);
+
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.header, "");
+        assert_eq!(frame.message_number, None);
+        assert!(frame.data.is_empty());
+
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = frame_bytes("state", 7, include_bytes!("example_state.bin"));
+        for len in 1..bytes.len() {
+            let err = FrameReader::new(&bytes[..len]).read_frame().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn hostile_length() {
+        let bytes = [0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0x07, 1, 2, 3, 4];
+        let err = FrameReader::new(&bytes[..]).read_frame().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
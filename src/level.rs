@@ -0,0 +1,146 @@
+use serde::Serialize;
+
+use crate::catalogue::scenarios::LevelInfo;
+use crate::state::{Actor, CharacterClass, State};
+
+/// How much harder than recommended the party wants its scenarios to be.
+#[derive(Serialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    VeryHard,
+}
+
+impl Difficulty {
+    /// How many levels the difficulty adds to the recommended scenario level.
+    pub fn adjustment(self) -> i32 {
+        match self {
+            Difficulty::Easy => -1,
+            Difficulty::Normal => 0,
+            Difficulty::Hard => 1,
+            Difficulty::VeryHard => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "very-hard" => Ok(Difficulty::VeryHard),
+            _ => Err(format!(
+                "unknown difficulty '{}', expected easy, normal, hard or very-hard",
+                s
+            )),
+        }
+    }
+}
+
+/// The scenario level to play at, given the levels of the characters in the party.
+///
+/// That's their average level, plus one when playing solo, halved and rounded up. It's then
+/// adjusted for `difficulty` and limited to the levels 0 to 7. Returns `None` for an empty
+/// party.
+pub fn recommended_level(levels: &[i32], solo: bool, difficulty: Difficulty) -> Option<i32> {
+    if levels.is_empty() {
+        return None;
+    }
+    let count = levels.len() as i32;
+    let total = levels.iter().sum::<i32>() + if solo { count } else { 0 };
+    // Round up, also for the negative levels a misbehaving client could send.
+    let level = -(-total).div_euclid(2 * count);
+    Some((level + difficulty.adjustment()).clamp(0, 7))
+}
+
+/// How the scenario level being played compares to the recommended one.
+#[derive(Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LevelRecommendation {
+    pub recommended: LevelInfo,
+    pub current: i32,
+    /// Whether `current` differs from the recommended level.
+    pub deviates: bool,
+}
+
+impl State {
+    /// The levels of the characters in the game, leaving out escorts and objectives.
+    pub fn character_levels(&self) -> Vec<i32> {
+        self.actors
+            .iter()
+            .filter_map(|actor| match actor {
+                Actor::Player(player)
+                    if !matches!(
+                        player.character_class,
+                        CharacterClass::Escort | CharacterClass::Objective
+                    ) =>
+                {
+                    Some(player.level)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Compares the scenario level to the one recommended for the party, or returns `None` if
+    /// there are no characters in the game yet.
+    pub fn level_recommendation(&self, difficulty: Difficulty) -> Option<LevelRecommendation> {
+        let level = recommended_level(&self.character_levels(), self.solo, difficulty)?;
+        Some(LevelRecommendation {
+            recommended: LevelInfo::new(level)?,
+            current: self.scenario_level,
+            deviates: self.scenario_level != level,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::example_state;
+
+    #[test]
+    fn recommended() {
+        let normal = Difficulty::Normal;
+        assert_eq!(recommended_level(&[], false, normal), None);
+        assert_eq!(recommended_level(&[1, 1, 1, 1], false, normal), Some(1));
+        assert_eq!(recommended_level(&[2, 2, 3], false, normal), Some(2));
+        assert_eq!(recommended_level(&[4, 4], false, normal), Some(2));
+        assert_eq!(recommended_level(&[4, 4], true, normal), Some(3));
+        assert_eq!(recommended_level(&[1], false, Difficulty::Easy), Some(0));
+        assert_eq!(
+            recommended_level(&[1], false, Difficulty::VeryHard),
+            Some(3)
+        );
+        assert_eq!(
+            recommended_level(&[9, 9], true, Difficulty::VeryHard),
+            Some(7)
+        );
+        assert_eq!(recommended_level(&[-5], false, Difficulty::Easy), Some(0));
+    }
+
+    #[test]
+    fn fixture() {
+        let mut state = example_state();
+        assert_eq!(state.character_levels(), [1, 1, 1, 1]);
+        let recommendation = state.level_recommendation(Difficulty::Normal).unwrap();
+        assert_eq!(recommendation.recommended.level, 1);
+        assert_eq!(recommendation.recommended.trap_damage, 3);
+        assert!(!recommendation.deviates);
+        assert!(
+            state
+                .level_recommendation(Difficulty::Hard)
+                .unwrap()
+                .deviates
+        );
+
+        state
+            .actors
+            .retain(|actor| matches!(actor, Actor::Monster(_)));
+        assert!(state.level_recommendation(Difficulty::Normal).is_none());
+    }
+}
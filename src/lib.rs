@@ -0,0 +1,30 @@
+//! An unofficial headless version of [Gloomhaven helper](http://esotericsoftware.com/gloomhaven-helper).
+//!
+//! The [`state`] module contains the game state model along with a decoder and encoder for the
+//! binary format the helper uses to exchange it, and [`frame`] reads the messages that carry it.
+//! [`supervisor`] keeps a connection to a helper server alive, while [`server`] takes its place
+//! by hosting a game itself. The latest state can be served over HTTP with [`api`], and
+//! [`diff`] describes what changed between two states. [`command`] sends changes back to the
+//! helper server, and [`session`] records everything received to a log file. The [`catalogue`]
+//! fills in the game data the helper only refers to by number, such as monster names and stats,
+//! [`level`] recommends a scenario level for the party. [`modifiers`] draws from the monsters'
+//! attack modifier deck, [`elements`] infuses, consumes and wanes the elements and [`round`]
+//! runs the round as a game master would, from drawing ability cards to the end of the round.
+
+pub mod api;
+pub mod catalogue;
+pub mod command;
+pub mod diff;
+pub mod elements;
+pub mod frame;
+pub mod level;
+pub mod modifiers;
+pub mod round;
+pub mod server;
+pub mod session;
+pub mod state;
+pub mod supervisor;
+
+pub use diff::{diff, StateChange};
+pub use frame::{Frame, FrameReader};
+pub use state::{from_bytes, read_varint, to_bytes, write_varint, Error, ErrorKind, State};
@@ -1,36 +1,355 @@
-use std::convert::TryInto;
-use std::io::prelude::*;
-use std::net::TcpStream;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-mod state;
+use headless_haven_helper::api::Api;
+use headless_haven_helper::diff::diff;
+use headless_haven_helper::server::{ConflictPolicy, Options, Server};
+use headless_haven_helper::session::{Entry, SessionReader, SessionWriter};
+use headless_haven_helper::state::{self, State};
+use headless_haven_helper::supervisor::{Backoff, Supervisor};
 
-use state::State;
+/// The port the helper listens on by default.
+const DEFAULT_LISTEN: &str = "0.0.0.0:58888";
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut stream = TcpStream::connect(std::env::var("HAVEN_HELPER_SERVER")?)?;
-
-    let mut buf = [0; 1024];
-    loop {
-        stream.read_exact(&mut buf[..2])?;
-        let str_len = i16::from_be_bytes(buf[..2].try_into().unwrap()) as usize;
-        stream.read_exact(&mut buf[..str_len])?;
-        let _state_str = std::str::from_utf8(&buf[..str_len]).unwrap();
-
-        let mut varint_buf = [0u8; 5];
-        stream.peek(&mut varint_buf)?;
-        let (varint_len, data_len) =
-            state::read_varint(&varint_buf).ok_or("unable to read data len header")?;
-
-        stream.read_exact(&mut buf[..varint_len + data_len as usize])?;
-        let mut data = vec![0u8; data_len as usize];
-        data.copy_from_slice(&buf[varint_len..varint_len + data_len as usize]);
-        if !data.is_empty() {
-            let message_number = i32::from_be_bytes(data[..4].try_into()?);
-            println!("message number {}", message_number);
-            let mut file = std::fs::File::create(format!("{}-state.bin", message_number))?;
-            file.write_all(&data[4..])?;
-            let state: State = state::from_bytes(&data[4..])?;
-            println!("{:#?}", state);
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Format {
+    /// The state's `Debug` representation, preceded by the message number.
+    Debug,
+    /// One pretty-printed JSON document per message.
+    Json,
+    /// One JSON document per line.
+    Ndjson,
+    /// Only what changed since the previous message, one change per line.
+    Events,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debug" => Ok(Format::Debug),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "events" => Ok(Format::Events),
+            _ => Err(format!(
+                "unknown format '{}', expected json, ndjson, events or debug",
+                s
+            )),
+        }
+    }
+}
+
+/// How much faster than recorded to replay a session, e.g. `4x`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Speed(f64);
+
+impl std::str::FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_suffix('x').unwrap_or(s).parse() {
+            Ok(speed) if speed > 0.0 && f64::is_finite(speed) => Ok(Speed(speed)),
+            _ => Err(format!("invalid speed '{}', expected e.g. 2x or 0.5x", s)),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Message<'a> {
+    message_number: i32,
+    state: &'a State,
+}
+
+enum Command {
+    /// Connect to a helper server and print every state it sends.
+    Watch {
+        format: Format,
+        max_retries: Option<u32>,
+        http: Option<String>,
+    },
+    /// Connect to a helper server and append every frame it sends to a session log.
+    Record {
+        out: PathBuf,
+        max_retries: Option<u32>,
+    },
+    /// Serve the frames of a session log to helper clients, with their original timing.
+    Replay {
+        log: PathBuf,
+        listen: String,
+        speed: Speed,
+    },
+    /// Host a game for helper clients.
+    Serve {
+        listen: String,
+        state: Option<PathBuf>,
+        conflicts: ConflictPolicy,
+    },
+}
+
+/// Splits `--name value` and `--name=value` arguments into pairs.
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Vec<(String, String)>, String> {
+    let mut options = Vec::new();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            return Err(format!("unexpected argument '{}'", arg));
+        }
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        let value = value
+            .or_else(|| args.next())
+            .ok_or(format!("{} requires a value", name))?;
+        options.push((name, value));
+    }
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, name))
+}
+
+fn parse_args() -> Result<Command, String> {
+    let mut args = std::env::args().skip(1).peekable();
+    let command = match args.peek() {
+        Some(arg) if !arg.starts_with("--") => args.next(),
+        _ => None,
+    };
+    let log = match command.as_deref() {
+        Some("replay") => args.next_if(|arg| !arg.starts_with("--")),
+        _ => None,
+    };
+    let options = parse_options(args)?;
+    let unexpected = |name: &str| Err(format!("unexpected argument '{}'", name));
+
+    match command.as_deref() {
+        None => {
+            let mut format = Format::Debug;
+            let mut max_retries = None;
+            let mut http = None;
+            for (name, value) in options {
+                match name.as_str() {
+                    "--format" => format = value.parse()?,
+                    "--max-retries" => max_retries = Some(parse_value(&name, &value)?),
+                    "--http" => http = Some(value),
+                    _ => return unexpected(&name),
+                }
+            }
+            Ok(Command::Watch {
+                format,
+                max_retries,
+                http,
+            })
+        }
+        Some("record") => {
+            let mut out = PathBuf::from("session.hhlog");
+            let mut max_retries = None;
+            for (name, value) in options {
+                match name.as_str() {
+                    "--out" => out = PathBuf::from(value),
+                    "--max-retries" => max_retries = Some(parse_value(&name, &value)?),
+                    _ => return unexpected(&name),
+                }
+            }
+            Ok(Command::Record { out, max_retries })
+        }
+        Some("replay") => {
+            let log = PathBuf::from(log.ok_or("replay requires a session log")?);
+            let mut listen = DEFAULT_LISTEN.to_string();
+            let mut speed = Speed(1.0);
+            for (name, value) in options {
+                match name.as_str() {
+                    "--listen" => listen = value,
+                    "--speed" => speed = value.parse()?,
+                    _ => return unexpected(&name),
+                }
+            }
+            Ok(Command::Replay { log, listen, speed })
         }
+        Some("serve") => {
+            let mut listen = DEFAULT_LISTEN.to_string();
+            let mut state = None;
+            let mut conflicts = ConflictPolicy::Reject;
+            for (name, value) in options {
+                match name.as_str() {
+                    "--listen" => listen = value,
+                    "--state" => state = Some(PathBuf::from(value)),
+                    "--conflicts" => conflicts = value.parse()?,
+                    _ => return unexpected(&name),
+                }
+            }
+            Ok(Command::Serve {
+                listen,
+                state,
+                conflicts,
+            })
+        }
+        Some(command) => Err(format!("unknown command '{}'", command)),
+    }
+}
+
+fn print_state(
+    format: Format,
+    message_number: i32,
+    previous: Option<&State>,
+    state: &State,
+) -> serde_json::Result<()> {
+    let message = Message {
+        message_number,
+        state,
+    };
+    match format {
+        Format::Debug => println!("{:#?}", state),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&message)?),
+        Format::Ndjson => println!("{}", serde_json::to_string(&message)?),
+        Format::Events => {
+            for change in previous
+                .map(|previous| diff(previous, state))
+                .unwrap_or_default()
+            {
+                println!("{}", change);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn watch(
+    format: Format,
+    max_retries: Option<u32>,
+    http: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let backoff = Backoff {
+        max_retries,
+        ..Backoff::default()
+    };
+    let supervisor = Supervisor::new(std::env::var("HAVEN_HELPER_SERVER")?, backoff);
+    let api = Api::with_commands(supervisor.commands());
+    if let Some(addr) = http {
+        let api = api.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = api.serve(&addr) {
+                eprintln!("HTTP API on {} failed: {}", addr, err);
+            }
+        });
+    }
+    let mut previous = None;
+
+    supervisor.run(|frame| {
+        if let Some(message_number) = frame.message_number {
+            if format == Format::Debug {
+                println!("{} message number {}", frame.header, message_number);
+            }
+            let state = match frame.state() {
+                Ok(state) => state,
+                Err(err) => {
+                    eprintln!("skipping message number {}: {}", message_number, err);
+                    return Ok(());
+                }
+            };
+            print_state(format, message_number, previous.as_ref(), &state)?;
+            api.publish(message_number, state.clone());
+            previous = Some(state);
+        }
+        Ok(())
+    })
+}
+
+fn record(out: &Path, max_retries: Option<u32>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = SessionWriter::append(out)?;
+    let backoff = Backoff {
+        max_retries,
+        ..Backoff::default()
+    };
+    let supervisor = Supervisor::new(std::env::var("HAVEN_HELPER_SERVER")?, backoff);
+    eprintln!("recording to {}", out.display());
+
+    supervisor.run(|frame| {
+        if let Some(message_number) = frame.message_number {
+            eprintln!("{} message number {}", frame.header, message_number);
+        }
+        log.write_entry(&Entry::now(frame))?;
+        Ok(())
+    })
+}
+
+fn replay(log: &Path, listen: &str, speed: Speed) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = SessionReader::new(BufReader::new(File::open(log)?))?;
+    let options = Options {
+        accept_updates: false,
+        ..Options::default()
+    };
+    let server = Arc::new(Server::new(State::default(), options)?);
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    let serving = {
+        let server = server.clone();
+        std::thread::spawn(move || server.serve(listener))
+    };
+
+    // Don't let the first frames go by before anyone is watching.
+    while server.clients() == 0 {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let mut previous = None;
+    for entry in entries {
+        let entry = entry?;
+        if let Some(previous) = previous {
+            let elapsed = Duration::from_millis(entry.received_at.saturating_sub(previous));
+            std::thread::sleep(elapsed.div_f64(speed.0));
+        }
+        previous = Some(entry.received_at);
+        if let Some(message_number) = entry.frame.message_number {
+            eprintln!("{} message number {}", entry.frame.header, message_number);
+        }
+        server.replay(entry.frame);
+    }
+    eprintln!("replay finished, serving the last state until stopped");
+    serving.join().unwrap()?;
+    Ok(())
+}
+
+fn serve(
+    listen: &str,
+    path: Option<PathBuf>,
+    conflicts: ConflictPolicy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let state = match &path {
+        Some(path) if path.exists() => state::from_bytes(&std::fs::read(path)?)?,
+        _ => State::default(),
+    };
+    let options = Options {
+        conflicts,
+        persist: path,
+        ..Options::default()
+    };
+    let server = Arc::new(Server::new(state, options)?);
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    server.serve(listener)?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    match parse_args()? {
+        Command::Watch {
+            format,
+            max_retries,
+            http,
+        } => watch(format, max_retries, http),
+        Command::Record { out, max_retries } => record(&out, max_retries),
+        Command::Replay { log, listen, speed } => replay(&log, &listen, speed),
+        Command::Serve {
+            listen,
+            state,
+            conflicts,
+        } => serve(&listen, state, conflicts),
     }
 }
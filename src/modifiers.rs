@@ -0,0 +1,722 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::state::{AttackModifier, State};
+
+/// How many Bless or Curse cards the deck can hold at once.
+pub const BLESS_CURSE_LIMIT: usize = 10;
+
+/// How an attack draws its modifiers.
+#[derive(Serialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum DrawMode {
+    /// Draw one card.
+    #[default]
+    Normal,
+    /// Draw two cards and use the better one.
+    Advantage,
+    /// Draw two cards and use the worse one.
+    Disadvantage,
+}
+
+impl DrawMode {
+    /// Picks the card that applies from the ones drawn, the first one if they're equally good.
+    fn choose(self, drawn: &[Card]) -> Card {
+        let (first, rest) = drawn.split_first().expect("at least one card is drawn");
+        rest.iter().fold(*first, |used, &card| match self {
+            DrawMode::Advantage if card.rank() > used.rank() => card,
+            DrawMode::Disadvantage if card.rank() < used.rank() => card,
+            _ => used,
+        })
+    }
+}
+
+impl std::str::FromStr for DrawMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(DrawMode::Normal),
+            "advantage" => Ok(DrawMode::Advantage),
+            "disadvantage" => Ok(DrawMode::Disadvantage),
+            _ => Err(format!(
+                "unknown draw mode '{}', expected normal, advantage or disadvantage",
+                s
+            )),
+        }
+    }
+}
+
+impl AttackModifier {
+    /// The attack value after applying the modifier to `base`.
+    pub fn apply(self, base: i32) -> i32 {
+        match self {
+            AttackModifier::Zero => base,
+            AttackModifier::Plus1 => base + 1,
+            AttackModifier::Plus2 => base + 2,
+            AttackModifier::Minus1 => (base - 1).max(0),
+            AttackModifier::Minus2 => (base - 2).max(0),
+            AttackModifier::Miss | AttackModifier::Curse => 0,
+            AttackModifier::Crit | AttackModifier::Bless => base * 2,
+        }
+    }
+
+    /// Orders the modifiers from worst to best, for choosing between two of them.
+    fn rank(self) -> u8 {
+        match self {
+            AttackModifier::Miss | AttackModifier::Curse => 0,
+            AttackModifier::Minus2 => 1,
+            AttackModifier::Minus1 => 2,
+            AttackModifier::Zero => 3,
+            AttackModifier::Plus1 => 4,
+            AttackModifier::Plus2 => 5,
+            AttackModifier::Crit | AttackModifier::Bless => 6,
+        }
+    }
+
+    /// Whether drawing the card means the deck is shuffled at the end of the round.
+    pub fn shuffles(self) -> bool {
+        matches!(self, AttackModifier::Miss | AttackModifier::Crit)
+    }
+
+    /// Whether the card leaves the game once drawn instead of going to the discard pile.
+    pub fn is_temporary(self) -> bool {
+        matches!(self, AttackModifier::Bless | AttackModifier::Curse)
+    }
+}
+
+/// A card in an attack modifier deck. The monster deck only holds the helper's
+/// [`AttackModifier`]s, while a [`CharacterDeck`] can also hold rolling modifiers.
+#[derive(Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Card {
+    Modifier(AttackModifier),
+    /// A rolling modifier adding its bonus to the attack, e.g. `Rolling(1)` for a rolling +1.
+    /// Another card is drawn after it.
+    Rolling(i32),
+}
+
+impl Card {
+    /// The attack value after applying the card to `base`.
+    pub fn apply(self, base: i32) -> i32 {
+        match self {
+            Card::Modifier(modifier) => modifier.apply(base),
+            Card::Rolling(bonus) => (base + bonus).max(0),
+        }
+    }
+
+    /// Orders the cards from worst to best, ranking a rolling modifier like the plain modifier
+    /// with the same bonus.
+    fn rank(self) -> u8 {
+        match self {
+            Card::Modifier(modifier) => modifier.rank(),
+            Card::Rolling(bonus) => (3 + bonus).clamp(1, 5) as u8,
+        }
+    }
+
+    /// Whether another card is drawn after this one and added to it.
+    pub fn rolls(self) -> bool {
+        matches!(self, Card::Rolling(_))
+    }
+
+    /// Whether drawing the card means the deck is shuffled at the end of the round.
+    pub fn shuffles(self) -> bool {
+        matches!(self, Card::Modifier(modifier) if modifier.shuffles())
+    }
+
+    /// Whether the card leaves the game once drawn instead of going to the discard pile.
+    pub fn is_temporary(self) -> bool {
+        matches!(self, Card::Modifier(modifier) if modifier.is_temporary())
+    }
+}
+
+impl From<AttackModifier> for Card {
+    fn from(modifier: AttackModifier) -> Self {
+        Card::Modifier(modifier)
+    }
+}
+
+impl PartialEq<AttackModifier> for Card {
+    fn eq(&self, other: &AttackModifier) -> bool {
+        *self == Card::Modifier(*other)
+    }
+}
+
+/// The cards drawn for one attack.
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct Draw {
+    /// Every card drawn, in order.
+    pub drawn: Vec<Card>,
+    /// The card that applies to the attack, besides any rolling modifiers.
+    pub used: Card,
+    /// The rolling modifiers drawn before it that add to the attack.
+    pub rolling: Vec<Card>,
+}
+
+impl Draw {
+    /// The attack value after adding up the rolling modifiers and applying the used card to
+    /// the result, so that e.g. a Crit doubles the rolling bonuses as well.
+    pub fn apply(&self, base: i32) -> i32 {
+        let rolled = self
+            .rolling
+            .iter()
+            .fold(base, |attack, card| card.apply(attack));
+        self.used.apply(rolled)
+    }
+}
+
+/// The cards drawn so far for an attack, which decide whether another one is needed.
+#[derive(Clone)]
+struct Drawing {
+    mode: DrawMode,
+    drawn: Vec<Card>,
+    ending: Vec<Card>,
+    rolling: Vec<Card>,
+}
+
+impl Drawing {
+    fn new(mode: DrawMode) -> Self {
+        Drawing {
+            mode,
+            drawn: Vec::new(),
+            ending: Vec::new(),
+            rolling: Vec::new(),
+        }
+    }
+
+    /// Adds the next card drawn, where `last` is whether there are no cards left to draw after
+    /// it, in which case even a rolling modifier ends the draw.
+    fn push(&mut self, card: Card, last: bool) {
+        self.drawn.push(card);
+        if card.rolls() && !last {
+            self.rolling.push(card);
+        } else {
+            self.ending.push(card);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        let needed = match self.mode {
+            DrawMode::Normal => 1,
+            DrawMode::Advantage | DrawMode::Disadvantage => 2,
+        };
+        self.ending.len() >= needed
+    }
+
+    fn finish(self) -> Option<Draw> {
+        if self.ending.is_empty() {
+            return None;
+        }
+        let used = self.mode.choose(&self.ending);
+        let rolling = match self.mode {
+            DrawMode::Disadvantage => Vec::new(),
+            DrawMode::Normal | DrawMode::Advantage => self.rolling,
+        };
+        Some(Draw {
+            drawn: self.drawn,
+            used,
+            rolling,
+        })
+    }
+}
+
+/// The draw pile, discard pile and shuffle flag of a deck, whichever type its cards are
+/// stored as.
+struct Piles<'a, C> {
+    deck: &'a mut Vec<C>,
+    discard: &'a mut Vec<C>,
+    needs_shuffle: &'a mut bool,
+}
+
+impl<C: Copy + Into<Card>> Piles<'_, C> {
+    fn draw<R: Rng + ?Sized>(&mut self, mode: DrawMode, rng: &mut R) -> Option<Draw> {
+        let mut drawing = Drawing::new(mode);
+        let mut taken = Vec::new();
+        while !drawing.is_done() {
+            match self.take(rng) {
+                Some(card) => {
+                    taken.push(card);
+                    drawing.push(card.into(), self.is_exhausted());
+                }
+                None => break,
+            }
+        }
+        let draw = drawing.finish()?;
+        for card in taken {
+            let drawn: Card = card.into();
+            if drawn.shuffles() {
+                *self.needs_shuffle = true;
+            }
+            if !drawn.is_temporary() {
+                self.discard.push(card);
+            }
+        }
+        Some(draw)
+    }
+
+    /// Whether both the deck and the discard pile are empty.
+    fn is_exhausted(&self) -> bool {
+        self.deck.is_empty() && self.discard.is_empty()
+    }
+
+    /// Takes the top card, shuffling the discard pile back in first if the deck is empty.
+    ///
+    /// Cards drawn by the current attack aren't in the discard pile yet, so they can't be
+    /// drawn again.
+    fn take<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<C> {
+        if self.deck.is_empty() {
+            self.shuffle(rng);
+        }
+        if self.deck.is_empty() {
+            None
+        } else {
+            Some(self.deck.remove(0))
+        }
+    }
+
+    fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.deck.append(self.discard);
+        self.deck.shuffle(rng);
+        *self.needs_shuffle = false;
+    }
+
+    fn end_of_round<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        let shuffle = *self.needs_shuffle;
+        if shuffle {
+            self.shuffle(rng);
+        }
+        shuffle
+    }
+}
+
+/// The monsters' attack modifier deck, operating directly on the `attack_modifiers`,
+/// `attack_modifiers_discard` and `needs_shuffle` fields of a [`State`].
+///
+/// The top of the deck is the first card in `attack_modifiers`.
+pub struct ModifierDeck<'a> {
+    state: &'a mut State,
+}
+
+impl State {
+    pub fn modifier_deck(&mut self) -> ModifierDeck<'_> {
+        ModifierDeck { state: self }
+    }
+}
+
+impl ModifierDeck<'_> {
+    /// Draws the modifiers for an attack, or returns `None` if there are no cards left even
+    /// after shuffling the discard pile back in.
+    ///
+    /// Drawn cards go to the discard pile, except for Bless and Curse which are removed from
+    /// the game. Drawing a Miss or a Crit sets `needs_shuffle`. With advantage or disadvantage,
+    /// the first card is used if both are equally good.
+    pub fn draw<R: Rng + ?Sized>(&mut self, mode: DrawMode, rng: &mut R) -> Option<Draw> {
+        self.piles().draw(mode, rng)
+    }
+
+    fn piles(&mut self) -> Piles<'_, AttackModifier> {
+        Piles {
+            deck: &mut self.state.attack_modifiers,
+            discard: &mut self.state.attack_modifiers_discard,
+            needs_shuffle: &mut self.state.needs_shuffle,
+        }
+    }
+
+    /// How many of `modifier` are in the deck.
+    pub fn count(&self, modifier: AttackModifier) -> usize {
+        self.state
+            .attack_modifiers
+            .iter()
+            .filter(|&&m| m == modifier)
+            .count()
+    }
+
+    /// Shuffles a Bless into the deck, returning `false` if it already holds
+    /// [`BLESS_CURSE_LIMIT`] of them.
+    pub fn add_bless<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        self.add(AttackModifier::Bless, rng)
+    }
+
+    /// Shuffles a Curse into the deck, returning `false` if it already holds
+    /// [`BLESS_CURSE_LIMIT`] of them.
+    pub fn add_curse<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        self.add(AttackModifier::Curse, rng)
+    }
+
+    fn add<R: Rng + ?Sized>(&mut self, modifier: AttackModifier, rng: &mut R) -> bool {
+        if self.count(modifier) >= BLESS_CURSE_LIMIT {
+            return false;
+        }
+        let deck = &mut self.state.attack_modifiers;
+        let position = rng.random_range(0..=deck.len());
+        deck.insert(position, modifier);
+        true
+    }
+
+    /// Moves the discard pile back into the deck and shuffles it.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.piles().shuffle(rng);
+    }
+
+    /// Shuffles the deck if a Miss or Crit was drawn this round, returning whether it did.
+    pub fn end_of_round<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        self.piles().end_of_round(rng)
+    }
+}
+
+/// A character's attack modifier deck, which unlike the monster deck can hold rolling
+/// modifiers. The helper doesn't track these decks, so it's kept apart from any [`State`].
+///
+/// The top of the deck is the first card in `cards`.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CharacterDeck {
+    pub cards: Vec<Card>,
+    pub discard: Vec<Card>,
+    pub needs_shuffle: bool,
+}
+
+impl CharacterDeck {
+    /// Draws the modifiers for an attack like [`ModifierDeck::draw`] does.
+    ///
+    /// Drawing a rolling modifier means drawing another card, until one that doesn't roll
+    /// ends the draw, and all of them are added up. Rolling modifiers don't count towards the
+    /// two cards drawn with advantage or disadvantage: with advantage they add to the better
+    /// card, while with disadvantage they're ignored. A rolling modifier also ends the draw
+    /// when there are no cards left to follow it.
+    pub fn draw<R: Rng + ?Sized>(&mut self, mode: DrawMode, rng: &mut R) -> Option<Draw> {
+        self.piles().draw(mode, rng)
+    }
+
+    /// Moves the discard pile back into the deck and shuffles it.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.piles().shuffle(rng);
+    }
+
+    /// Shuffles the deck if a Miss or Crit was drawn this round, returning whether it did.
+    pub fn end_of_round<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        self.piles().end_of_round(rng)
+    }
+
+    /// Works out the odds of the next attack like [`State::modifier_odds`] does.
+    pub fn odds(&self, base_attack: i32, mode: DrawMode) -> Option<ModifierOdds> {
+        odds(&self.cards, &self.discard, base_attack, mode)
+    }
+
+    fn piles(&mut self) -> Piles<'_, Card> {
+        Piles {
+            deck: &mut self.cards,
+            discard: &mut self.discard,
+            needs_shuffle: &mut self.needs_shuffle,
+        }
+    }
+}
+
+/// The chances of each attack value for the next attack, as given by [`State::modifier_odds`].
+///
+/// Attack values are before shields, and every order the remaining cards could be in is
+/// considered equally likely.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ModifierOdds {
+    pub base_attack: i32,
+    pub mode: DrawMode,
+    /// Each possible attack value with its probability, from lowest to highest.
+    pub outcomes: Vec<Outcome>,
+    pub expected_attack: f64,
+    /// The probability of the attack using a Miss or a Curse.
+    pub miss: f64,
+    /// The probability of the attack using a Crit or a Bless.
+    pub critical: f64,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub attack: i32,
+    pub probability: f64,
+}
+
+impl ModifierOdds {
+    /// The probability of the attack being at least `attack`, e.g. enough to kill a standee
+    /// with that much health left.
+    pub fn at_least(&self, attack: i32) -> f64 {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.attack >= attack)
+            .map(|outcome| outcome.probability)
+            .sum()
+    }
+}
+
+impl State {
+    /// Works out the odds of the next attack with a base value of `base_attack`, drawing the
+    /// way [`ModifierDeck::draw`] does. Returns `None` if there are no cards to draw.
+    pub fn modifier_odds(&self, base_attack: i32, mode: DrawMode) -> Option<ModifierOdds> {
+        odds(
+            &self.attack_modifiers,
+            &self.attack_modifiers_discard,
+            base_attack,
+            mode,
+        )
+    }
+}
+
+/// The odds of the next attack drawn from `deck`, with `discard` shuffled in once it runs out.
+fn odds<C: Copy + Into<Card>>(
+    deck: &[C],
+    discard: &[C],
+    base_attack: i32,
+    mode: DrawMode,
+) -> Option<ModifierOdds> {
+    let cards = |cards: &[C]| cards.iter().map(|&card| card.into()).collect::<Vec<Card>>();
+    let mut draws = Vec::new();
+    deal(
+        &cards(deck),
+        &cards(discard),
+        Drawing::new(mode),
+        (1, 1),
+        &mut draws,
+    );
+    if draws.is_empty() {
+        return None;
+    }
+
+    // Counted over a common denominator, so that the probabilities are only rounded once.
+    let total = draws.iter().fold(1, |total, &(_, (_, of))| lcm(total, of));
+    let mut counts = std::collections::BTreeMap::new();
+    let (mut miss, mut critical) = (0, 0);
+    for (draw, (ways, of)) in &draws {
+        let count = ways * (total / of);
+        *counts.entry(draw.apply(base_attack)).or_insert(0) += count;
+        match draw.used {
+            Card::Modifier(AttackModifier::Miss | AttackModifier::Curse) => miss += count,
+            Card::Modifier(AttackModifier::Crit | AttackModifier::Bless) => critical += count,
+            _ => {}
+        }
+    }
+    let total = total as f64;
+    let outcomes: Vec<_> = counts
+        .into_iter()
+        .map(|(attack, count)| Outcome {
+            attack,
+            probability: count as f64 / total,
+        })
+        .collect();
+    Some(ModifierOdds {
+        base_attack,
+        mode,
+        expected_attack: outcomes
+            .iter()
+            .map(|outcome| outcome.attack as f64 * outcome.probability)
+            .sum(),
+        outcomes,
+        miss: miss as f64 / total,
+        critical: critical as f64 / total,
+    })
+}
+
+/// Collects every way the rest of `drawing` could go with the cards in `deck`, each with its
+/// probability as a fraction, shuffling the discard pile in for the cards after the last one.
+fn deal(
+    deck: &[Card],
+    discard: &[Card],
+    drawing: Drawing,
+    probability: (u128, u128),
+    draws: &mut Vec<(Draw, (u128, u128))>,
+) {
+    if deck.is_empty() && !discard.is_empty() {
+        return deal(discard, &[], drawing, probability, draws);
+    }
+    if drawing.is_done() || deck.is_empty() {
+        draws.extend(drawing.finish().map(|draw| (draw, probability)));
+        return;
+    }
+    // Copies of the same card lead to the same draws, so each is only followed once.
+    for (i, &card) in deck.iter().enumerate() {
+        if deck[..i].contains(&card) {
+            continue;
+        }
+        let copies = deck.iter().filter(|&&c| c == card).count();
+        let mut rest = deck.to_vec();
+        rest.remove(i);
+        let mut next = drawing.clone();
+        next.push(card, rest.is_empty() && discard.is_empty());
+        let (ways, of) = probability;
+        let chance = (ways * copies as u128, of * deck.len() as u128);
+        deal(&rest, discard, next, chance, draws);
+    }
+}
+
+fn lcm(a: u128, b: u128) -> u128 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::example_state;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn draw() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut state = example_state();
+        let mut deck = state.modifier_deck();
+        let normal = deck.draw(DrawMode::Normal, &mut rng).unwrap();
+        assert_eq!(normal.drawn, [AttackModifier::Minus1]);
+        assert_eq!(normal.used, AttackModifier::Minus1);
+        // Crit, then Plus1.
+        let advantage = deck.draw(DrawMode::Advantage, &mut rng).unwrap();
+        assert_eq!(advantage.used, AttackModifier::Crit);
+        // Zero, then Minus2.
+        let disadvantage = deck.draw(DrawMode::Disadvantage, &mut rng).unwrap();
+        assert_eq!(disadvantage.used, AttackModifier::Minus2);
+
+        assert!(state.needs_shuffle);
+        assert_eq!(state.attack_modifiers.len(), 14);
+        assert_eq!(state.attack_modifiers_discard.len(), 6);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(state.modifier_deck().end_of_round(&mut rng));
+        assert!(!state.needs_shuffle);
+        assert_eq!(state.attack_modifiers.len(), 20);
+        assert!(state.attack_modifiers_discard.is_empty());
+        assert!(!state.modifier_deck().end_of_round(&mut rng));
+    }
+
+    #[test]
+    fn bless_and_curse() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut state = example_state();
+        state.attack_modifiers.clear();
+        let mut deck = state.modifier_deck();
+        for _ in 0..BLESS_CURSE_LIMIT {
+            assert!(deck.add_curse(&mut rng));
+        }
+        assert!(!deck.add_curse(&mut rng));
+        assert!(deck.add_bless(&mut rng));
+        assert_eq!(deck.count(AttackModifier::Curse), BLESS_CURSE_LIMIT);
+
+        for _ in 0..=BLESS_CURSE_LIMIT {
+            deck.draw(DrawMode::Normal, &mut rng).unwrap();
+        }
+        // Bless and Curse leave the game, so the next draw reshuffles the original discard pile.
+        assert_eq!(deck.count(AttackModifier::Curse), 0);
+        let draw = deck.draw(DrawMode::Advantage, &mut rng).unwrap();
+        assert_eq!(draw.drawn, [AttackModifier::Plus1]);
+        assert_eq!(state.attack_modifiers, []);
+        assert_eq!(state.attack_modifiers_discard, [AttackModifier::Plus1]);
+
+        state.attack_modifiers_discard.clear();
+        assert_eq!(state.modifier_deck().draw(DrawMode::Normal, &mut rng), None);
+    }
+
+    #[test]
+    fn rolling() {
+        use AttackModifier::*;
+        use Card::Rolling;
+
+        let deck = |cards: &[Card]| CharacterDeck {
+            cards: cards.to_vec(),
+            ..CharacterDeck::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut character = deck(&[
+            Rolling(1),
+            Rolling(2),
+            Crit.into(),
+            Zero.into(),
+            Rolling(1),
+            Minus1.into(),
+        ]);
+        let normal = character.draw(DrawMode::Normal, &mut rng).unwrap();
+        assert_eq!(normal.drawn, [Rolling(1), Rolling(2), Crit.into()]);
+        assert_eq!(normal.used, Crit);
+        assert_eq!(normal.rolling, [Rolling(1), Rolling(2)]);
+        assert_eq!(normal.apply(2), 10);
+        assert!(character.needs_shuffle);
+        let advantage = character.draw(DrawMode::Advantage, &mut rng).unwrap();
+        assert_eq!(advantage.drawn, [Zero.into(), Rolling(1), Minus1.into()]);
+        assert_eq!(advantage.apply(2), 3);
+        assert!(character.cards.is_empty());
+        assert_eq!(character.discard.len(), 6);
+
+        let mut character = deck(&[Rolling(2), Plus1.into(), Zero.into()]);
+        let disadvantage = character.draw(DrawMode::Disadvantage, &mut rng).unwrap();
+        assert!(disadvantage.rolling.is_empty());
+        assert_eq!(disadvantage.apply(2), 2);
+
+        // With nothing left to follow it, a rolling modifier ends the draw.
+        let mut character = deck(&[Rolling(2)]);
+        let last = character.draw(DrawMode::Normal, &mut rng).unwrap();
+        assert_eq!(last.used, Rolling(2));
+        assert_eq!(last.apply(1), 3);
+
+        let character = deck(&[Rolling(1), Zero.into(), Plus1.into()]);
+        let odds = character.odds(3, DrawMode::Normal).unwrap();
+        let attacks: Vec<_> = odds.outcomes.iter().map(|o| o.attack).collect();
+        assert_eq!(attacks, [3, 4, 5]);
+        assert_eq!(odds.at_least(5), 1.0 / 6.0);
+        assert!((odds.expected_attack - 23.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn apply() {
+        assert_eq!(AttackModifier::Plus2.apply(3), 5);
+        assert_eq!(AttackModifier::Minus2.apply(1), 0);
+        assert_eq!(AttackModifier::Crit.apply(3), 6);
+        assert_eq!(AttackModifier::Curse.apply(3), 0);
+        assert_eq!("disadvantage".parse(), Ok(DrawMode::Disadvantage));
+    }
+
+    #[test]
+    fn odds() {
+        let mut state = example_state();
+        state.attack_modifiers = vec![
+            AttackModifier::Miss,
+            AttackModifier::Zero,
+            AttackModifier::Plus1,
+            AttackModifier::Crit,
+        ];
+        let odds = state.modifier_odds(3, DrawMode::Normal).unwrap();
+        let attacks: Vec<_> = odds.outcomes.iter().map(|o| o.attack).collect();
+        assert_eq!(attacks, [0, 3, 4, 6]);
+        assert_eq!(odds.expected_attack, 13.0 / 4.0);
+        assert_eq!(odds.at_least(4), 0.5);
+
+        // Half of the 12 ordered pairs have the Crit, and only 2 have neither it nor the Plus1.
+        let advantage = state.modifier_odds(3, DrawMode::Advantage).unwrap();
+        assert_eq!(advantage.critical, 0.5);
+        assert!((advantage.at_least(4) - 10.0 / 12.0).abs() < 1e-9);
+        assert_eq!(advantage.miss, 0.0);
+        let disadvantage = state.modifier_odds(3, DrawMode::Disadvantage).unwrap();
+        assert_eq!(disadvantage.miss, 0.5);
+        assert_eq!(disadvantage.critical, 0.0);
+
+        // One card left: the second one comes from the reshuffled discard pile.
+        state.attack_modifiers = vec![AttackModifier::Curse];
+        state.attack_modifiers_discard = vec![AttackModifier::Plus1, AttackModifier::Minus1];
+        let advantage = state.modifier_odds(2, DrawMode::Advantage).unwrap();
+        assert_eq!(
+            advantage.outcomes,
+            [
+                Outcome {
+                    attack: 1,
+                    probability: 0.5
+                },
+                Outcome {
+                    attack: 3,
+                    probability: 0.5
+                },
+            ]
+        );
+
+        state.attack_modifiers.clear();
+        let normal = state.modifier_odds(2, DrawMode::Normal).unwrap();
+        assert_eq!(normal.expected_attack, 2.0);
+        state.attack_modifiers_discard.clear();
+        assert_eq!(state.modifier_odds(2, DrawMode::Normal), None);
+    }
+}
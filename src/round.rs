@@ -0,0 +1,575 @@
+use std::fmt;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::catalogue::abilities::AbilityCatalogue;
+use crate::command::{instance_mut, player_mut};
+use crate::diff::Target;
+use crate::state::{Ability, AbilityDeck, Actor, Condition, MonsterInstance, MonsterType, State};
+
+/// One figure's turn in the round, as listed by [`State::turn_order`].
+#[derive(Serialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Turn {
+    /// The initiative the turn is taken at, or `None` if it isn't known yet: for a player that
+    /// hasn't picked their cards or a monster without an ability card.
+    pub initiative: Option<i32>,
+    pub target: Target,
+}
+
+impl State {
+    /// The order in which players, summons and monster standees take their turns this round.
+    ///
+    /// Monster initiative comes from the ability card catalogue. On equal initiative players go
+    /// before monsters and monsters go in the order of their ability card numbers, otherwise
+    /// the order the helper sent the actors in is kept. Figures without a known initiative go
+    /// last, which includes monsters missing from the catalogue. Summons act right before their owner. Within a monster group, elites act before
+    /// normal standees if `elites_first` is set, and standees act in the order of their numbers.
+    ///
+    /// Exhausted players and monsters without standees don't take a turn.
+    pub fn turn_order(&self) -> Vec<Turn> {
+        let mut actors: Vec<_> = self
+            .actors
+            .iter()
+            .filter(|actor| match actor {
+                Actor::Player(player) => !player.exhausted,
+                Actor::Monster(monster) => !monster.instances.is_empty(),
+            })
+            .collect();
+        actors.sort_by_key(|actor| match actor {
+            Actor::Player(player) => {
+                let initiative = Some(player.initiative).filter(|&initiative| initiative > 0);
+                (initiative.is_none(), initiative, 0, 0)
+            }
+            Actor::Monster(monster) => {
+                let initiative = monster.initiative();
+                (initiative.is_none(), initiative, 1, monster.ability.value)
+            }
+        });
+
+        let mut turns = Vec::new();
+        for actor in actors {
+            match actor {
+                Actor::Player(player) => {
+                    let initiative = Some(player.initiative).filter(|&initiative| initiative > 0);
+                    let owner = player.character_class;
+                    for summon in self.acting_order(&player.instances) {
+                        turns.push(Turn {
+                            initiative,
+                            target: Target::Summon {
+                                owner,
+                                number: summon.number,
+                            },
+                        });
+                    }
+                    turns.push(Turn {
+                        initiative,
+                        target: Target::Player(owner),
+                    });
+                }
+                Actor::Monster(monster) => {
+                    for instance in self.acting_order(&monster.instances) {
+                        turns.push(Turn {
+                            initiative: monster.initiative(),
+                            target: Target::MonsterInstance {
+                                monster_id: monster.id,
+                                number: instance.number,
+                            },
+                        });
+                    }
+                }
+            }
+        }
+        turns
+    }
+
+    fn acting_order<'a>(&self, instances: &'a [MonsterInstance]) -> Vec<&'a MonsterInstance> {
+        let mut instances: Vec<_> = instances.iter().collect();
+        instances.sort_by_key(|instance| {
+            let elite = self.elites_first && instance.tpe == MonsterType::Elite;
+            (!elite, instance.number)
+        });
+        instances
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum RoundError {
+    /// The monster ability cards have already been drawn this round.
+    AlreadyDrawn,
+    /// The player, summon or monster standee isn't in the state.
+    UnknownTarget(Target),
+    /// The figure has already ended its turn this round.
+    TurnAlreadyEnded(Target),
+    /// The monster with this id has standees but isn't in the monster catalogue, so its
+    /// ability deck isn't known.
+    UnknownMonster(i32),
+    /// The ability deck with this id belongs to a monster with standees but isn't in the
+    /// ability catalogue, so its cards' initiative isn't known.
+    UnknownDeck(i32),
+}
+
+impl Condition {
+    /// Whether the condition wears off at the end of the next turn of the figure that has it.
+    pub fn expires_at_end_of_turn(self) -> bool {
+        !matches!(
+            self,
+            Condition::Poisoned | Condition::Wounded | Condition::Summoned
+        )
+    }
+}
+
+impl State {
+    /// Starts the round by drawing an ability card for every monster with standees, once the
+    /// players have picked their initiative. Monsters sharing an ability deck get the same
+    /// card, and drawing a card with the shuffle symbol marks its deck for shuffling at the end
+    /// of the round. Clears `can_draw` until [`end_round`](State::end_round).
+    ///
+    /// Fails without drawing anything if a monster with standees or its ability deck is missing
+    /// from the [current](crate::catalogue::monsters::MonsterCatalogue::current) catalogues.
+    pub fn start_round<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Result<(), RoundError> {
+        if !self.can_draw {
+            return Err(RoundError::AlreadyDrawn);
+        }
+        for actor in &self.actors {
+            let monster = match actor {
+                Actor::Monster(monster) if !monster.instances.is_empty() => monster,
+                _ => continue,
+            };
+            let info = monster
+                .info()
+                .ok_or(RoundError::UnknownMonster(monster.id))?;
+            if monster.deck().is_none() {
+                return Err(RoundError::UnknownDeck(info.deck));
+            }
+        }
+        let mut drawn = Vec::new();
+        for actor in &mut self.actors {
+            let monster = match actor {
+                Actor::Monster(monster) if !monster.instances.is_empty() => monster,
+                _ => continue,
+            };
+            let deck_id = match monster.deck() {
+                Some(deck) => deck.id,
+                None => continue,
+            };
+            let card = match drawn.iter().find(|&&(id, _)| id == deck_id) {
+                Some(&(_, card)) => Some(card),
+                None => {
+                    let deck = self
+                        .ability_decks
+                        .iter_mut()
+                        .find(|deck| deck.id == deck_id);
+                    let card = deck.and_then(|deck| draw_ability(deck, rng));
+                    drawn.extend(card.map(|card| (deck_id, card)));
+                    card
+                }
+            };
+            if let Some(card) = card {
+                monster.ability = card;
+            }
+        }
+        self.can_draw = false;
+        Ok(())
+    }
+
+    /// Ends the turn of a player, summon or monster standee.
+    ///
+    /// If `expire_conditions` is set, the conditions that wear off at the end of a turn are
+    /// moved to `conditions_expired`, except for the ones gained during this turn. Players are
+    /// marked as having completed their turn, summons and monster standees are added to
+    /// `ended_turns`, and monsters are marked as having completed their turn once all of their
+    /// standees in [`turn_order`](State::turn_order) have ended theirs.
+    ///
+    /// Fails for a figure that has already ended its turn this round: a player or monster whose
+    /// turn is complete, a summon whose owner's turn is complete, or a summon or standee in
+    /// `ended_turns`.
+    pub fn end_turn(&mut self, target: Target) -> Result<(), RoundError> {
+        if self.turn_completed(target)? || self.ended_turns.contains(&target) {
+            return Err(RoundError::TurnAlreadyEnded(target));
+        }
+        let expire = self.expire_conditions;
+        let (conditions, current_turn, expired) = match target {
+            Target::Player(class) => {
+                let player = player_mut(self, class).ok_or(RoundError::UnknownTarget(target))?;
+                player.turn_completed = true;
+                (
+                    &mut player.conditions,
+                    &mut player.conditions_current_turn,
+                    &mut player.conditions_expired,
+                )
+            }
+            _ => {
+                let instance =
+                    instance_mut(self, target).ok_or(RoundError::UnknownTarget(target))?;
+                (
+                    &mut instance.conditions,
+                    &mut instance.conditions_current_turn,
+                    &mut instance.conditions_expired,
+                )
+            }
+        };
+        if expire {
+            *expired = expire_conditions(conditions, current_turn);
+        }
+        current_turn.clear();
+        if let Target::Player(_) = target {
+            return Ok(());
+        }
+        self.ended_turns.push(target);
+
+        let monster_id = target_monster_id(target);
+        let group_completed = monster_id.is_some()
+            && self
+                .turn_order()
+                .iter()
+                .filter(|turn| target_monster_id(turn.target) == monster_id)
+                .all(|turn| self.ended_turns.contains(&turn.target));
+        if group_completed {
+            for actor in &mut self.actors {
+                if let Actor::Monster(monster) = actor {
+                    if Some(monster.id) == monster_id {
+                        monster.turn_completed = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the player or monster group `target` belongs to has completed its turn.
+    fn turn_completed(&self, target: Target) -> Result<bool, RoundError> {
+        self.actors
+            .iter()
+            .find_map(|actor| match (actor, target) {
+                (Actor::Player(player), Target::Player(class))
+                | (Actor::Player(player), Target::Summon { owner: class, .. })
+                    if player.character_class == class =>
+                {
+                    Some(player.turn_completed)
+                }
+                (Actor::Monster(monster), Target::MonsterInstance { monster_id, .. })
+                    if monster.id == monster_id =>
+                {
+                    Some(monster.turn_completed)
+                }
+                _ => None,
+            })
+            .ok_or(RoundError::UnknownTarget(target))
+    }
+
+    /// Ends the round: elements wane, the attack modifier deck and the ability decks marked for
+    /// shuffling are shuffled, turns are reset and the round number goes up, ready for the
+    /// next [`start_round`](State::start_round). Standees summoned this round lose their
+    /// Summoned condition.
+    pub fn end_round<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mut elements = self.elements();
+        elements.end_of_round_wane();
+        self.set_elements(&elements);
+        self.modifier_deck().end_of_round(rng);
+        for deck in &mut self.ability_decks {
+            if deck.shuffle {
+                deck.abilities.append(&mut deck.abilities_discard);
+                deck.abilities.shuffle(rng);
+                deck.shuffle = false;
+                deck.shown_ability = None;
+            }
+        }
+        for actor in &mut self.actors {
+            let (turn_completed, instances) = match actor {
+                Actor::Player(player) => (&mut player.turn_completed, &mut player.instances),
+                Actor::Monster(monster) => (&mut monster.turn_completed, &mut monster.instances),
+            };
+            *turn_completed = false;
+            for instance in instances {
+                instance
+                    .conditions
+                    .retain(|&condition| condition != Condition::Summoned);
+            }
+        }
+        self.round += 1;
+        self.can_draw = true;
+        self.ended_turns.clear();
+    }
+}
+
+/// Moves the top card of `deck` to its discard pile and shows it, shuffling the discard pile
+/// back in first if the deck is empty.
+fn draw_ability<R: Rng + ?Sized>(deck: &mut AbilityDeck, rng: &mut R) -> Option<Ability> {
+    if deck.abilities.is_empty() {
+        deck.abilities.append(&mut deck.abilities_discard);
+        deck.abilities.shuffle(rng);
+    }
+    if deck.abilities.is_empty() {
+        return None;
+    }
+    let value = deck.abilities.remove(0);
+    deck.abilities_discard.push(value);
+    let card = AbilityCatalogue::current()
+        .deck(deck.id)
+        .and_then(|info| info.card(value));
+    if card.is_some_and(|card| card.shuffle) {
+        deck.shuffle = true;
+    }
+    let ability = Ability { value };
+    deck.shown_ability = Some(ability);
+    Some(ability)
+}
+
+fn target_monster_id(target: Target) -> Option<i32> {
+    match target {
+        Target::MonsterInstance { monster_id, .. } => Some(monster_id),
+        _ => None,
+    }
+}
+
+/// Removes the conditions that wear off at the end of a turn, except for the ones in `keep`,
+/// returning the ones removed.
+fn expire_conditions(conditions: &mut Vec<Condition>, keep: &[Condition]) -> Vec<Condition> {
+    let (expired, kept) = conditions
+        .iter()
+        .partition(|&&condition| condition.expires_at_end_of_turn() && !keep.contains(&condition));
+    *conditions = kept;
+    expired
+}
+
+impl fmt::Display for RoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundError::AlreadyDrawn => write!(f, "the ability cards have already been drawn"),
+            RoundError::UnknownTarget(target) => write!(f, "{} is not in the game", target),
+            RoundError::TurnAlreadyEnded(target) => {
+                write!(f, "{} has already ended its turn", target)
+            }
+            RoundError::UnknownMonster(id) => {
+                write!(f, "monster {} is not in the monster catalogue", id)
+            }
+            RoundError::UnknownDeck(id) => {
+                write!(f, "ability deck {} is not in the ability catalogue", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RoundError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::example_state;
+    use crate::state::{CharacterClass, ElementState};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn targets(state: &State) -> Vec<String> {
+        let turns = state.turn_order();
+        turns.iter().map(|turn| turn.target.to_string()).collect()
+    }
+
+    #[test]
+    fn turn_order() {
+        let mut state = example_state();
+        let turns = state.turn_order();
+        assert_eq!(turns[0].initiative, Some(10));
+        assert_eq!(turns[6].initiative, Some(12));
+        assert_eq!(
+            targets(&state),
+            [
+                "Cultist #1",
+                "Cultist #2",
+                "Cultist #3",
+                "Cultist #4",
+                "Cultist #5",
+                "Cultist #6",
+                "Spellweaver",
+                "Scoundrel",
+                "Brute",
+                "Mindthief",
+                "Living Bones #1",
+                "Living Bones #7",
+                "Living Bones #10",
+            ]
+        );
+
+        if let Actor::Monster(monster) = &mut state.actors[5] {
+            // No card drawn for the Living Bones yet.
+            monster.ability.value = 0;
+            monster.instances[0].tpe = MonsterType::Normal;
+        }
+        let summon = match &state.actors[3] {
+            Actor::Monster(monster) => MonsterInstance {
+                tpe: MonsterType::Summon,
+                ..monster.instances[0].clone()
+            },
+            _ => unreachable!(),
+        };
+        if let Actor::Player(player) = &mut state.actors[4] {
+            player.instances = vec![summon];
+        }
+        // The same initiative as the Cultists.
+        if let Actor::Player(player) = &mut state.actors[1] {
+            player.initiative = 10;
+        }
+        if let Actor::Player(player) = &mut state.actors[2] {
+            player.exhausted = true;
+        }
+        let expected = [
+            "Scoundrel",
+            "Cultist #1",
+            "Cultist #2",
+            "Cultist #3",
+            "Cultist #4",
+            "Cultist #5",
+            "Cultist #6",
+            "Spellweaver",
+            "Mindthief's summon #1",
+            "Mindthief",
+            "Living Bones #7",
+            "Living Bones #1",
+            "Living Bones #10",
+        ];
+        assert_eq!(targets(&state), expected);
+        assert_eq!(state.turn_order()[12].initiative, None);
+        assert_eq!(
+            state.turn_order()[9].target,
+            Target::Player(CharacterClass::Mindthief)
+        );
+
+        state.elites_first = false;
+        assert_eq!(
+            targets(&state)[10..],
+            ["Living Bones #1", "Living Bones #7", "Living Bones #10"]
+        );
+    }
+
+    fn player(state: &mut State, index: usize) -> &mut crate::state::Player {
+        match &mut state.actors[index] {
+            Actor::Player(player) => player,
+            _ => unreachable!(),
+        }
+    }
+
+    fn monster(state: &mut State, index: usize) -> &mut crate::state::Monster {
+        match &mut state.actors[index] {
+            Actor::Monster(monster) => monster,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn start_round() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut state = example_state();
+        assert_eq!(state.start_round(&mut rng), Err(RoundError::AlreadyDrawn));
+
+        state.can_draw = true;
+        state.start_round(&mut rng).unwrap();
+        assert!(!state.can_draw);
+        assert_eq!(monster(&mut state, 3).ability, Ability { value: 32 });
+        assert_eq!(monster(&mut state, 5).ability, Ability { value: 116 });
+        // No standees, so no card.
+        assert_eq!(monster(&mut state, 6).ability, Ability { value: 0 });
+        assert_eq!(state.ability_decks[0].abilities_discard, [35, 39, 32]);
+        assert_eq!(
+            state.ability_decks[0].shown_ability,
+            Some(Ability { value: 32 })
+        );
+        assert_eq!(state.ability_decks[1].shown_ability, None);
+        assert_eq!(state.start_round(&mut rng), Err(RoundError::AlreadyDrawn));
+
+        let mut state = example_state();
+        state.can_draw = true;
+        monster(&mut state, 5).id = 99;
+        assert_eq!(
+            state.start_round(&mut rng),
+            Err(RoundError::UnknownMonster(99))
+        );
+        assert!(state.can_draw);
+        assert_eq!(state.ability_decks, example_state().ability_decks);
+    }
+
+    #[test]
+    fn end_turn() {
+        let mut state = example_state();
+        let brute = player(&mut state, 2);
+        brute.conditions = vec![
+            Condition::Stunned,
+            Condition::Poisoned,
+            Condition::Invisible,
+        ];
+        brute.conditions_current_turn = vec![Condition::Invisible];
+        let target = Target::Player(CharacterClass::Brute);
+        state.end_turn(target).unwrap();
+        let brute = player(&mut state, 2);
+        assert_eq!(
+            brute.conditions,
+            [Condition::Poisoned, Condition::Invisible]
+        );
+        assert_eq!(brute.conditions_expired, [Condition::Stunned]);
+        assert_eq!(brute.conditions_current_turn, []);
+        assert!(brute.turn_completed);
+        assert_eq!(
+            state.end_turn(target),
+            Err(RoundError::TurnAlreadyEnded(target))
+        );
+
+        let bones = |number| Target::MonsterInstance {
+            monster_id: 19,
+            number,
+        };
+        // The last standee in turn order ending first doesn't complete the group.
+        state.end_turn(bones(10)).unwrap();
+        assert!(!monster(&mut state, 5).turn_completed);
+        assert_eq!(
+            state.end_turn(bones(10)),
+            Err(RoundError::TurnAlreadyEnded(bones(10)))
+        );
+        state.end_turn(bones(1)).unwrap();
+        assert!(!monster(&mut state, 5).turn_completed);
+        state.end_turn(bones(7)).unwrap();
+        assert!(monster(&mut state, 5).turn_completed);
+        assert_eq!(state.ended_turns, [bones(10), bones(1), bones(7)]);
+        assert_eq!(
+            state.end_turn(bones(1)),
+            Err(RoundError::TurnAlreadyEnded(bones(1)))
+        );
+        let unknown = Target::Player(CharacterClass::Tinkerer);
+        assert_eq!(
+            state.end_turn(unknown),
+            Err(RoundError::UnknownTarget(unknown))
+        );
+    }
+
+    #[test]
+    fn end_round() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut state = example_state();
+        player(&mut state, 2).turn_completed = true;
+        monster(&mut state, 5).turn_completed = true;
+        state.ended_turns = vec![Target::MonsterInstance {
+            monster_id: 19,
+            number: 10,
+        }];
+        monster(&mut state, 5).instances[2]
+            .conditions
+            .push(Condition::Summoned);
+        state.ability_decks[3].shuffle = true;
+        state.needs_shuffle = true;
+
+        state.end_round(&mut rng);
+        assert_eq!(state.round, 6);
+        assert!(state.can_draw);
+        assert_eq!(state.light, ElementState::Waning);
+        assert!(!state.needs_shuffle);
+        assert_eq!(state.attack_modifiers.len(), 20);
+        let deck = &state.ability_decks[3];
+        assert_eq!((deck.abilities.len(), deck.abilities_discard.len()), (8, 0));
+        assert_eq!(deck.shown_ability, None);
+        assert_eq!(state.ability_decks[0].abilities_discard.len(), 2);
+        assert!(!player(&mut state, 2).turn_completed);
+        assert!(!monster(&mut state, 5).turn_completed);
+        assert_eq!(state.ended_turns, []);
+        assert_eq!(monster(&mut state, 5).instances[2].conditions, []);
+    }
+}
@@ -0,0 +1,486 @@
+use std::io::{self, BufReader};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::frame::{Frame, FrameReader};
+use crate::state::{self, Actor, State};
+
+/// How long a client may block a broadcast before it's dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do with an update from a client that hadn't seen the latest state when it made it.
+///
+/// Clients number their updates one past the state they were based on, so an update is stale
+/// if its message number isn't greater than the server's.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConflictPolicy {
+    /// Drop the update and send the current state back to the client.
+    Reject,
+    /// Apply the update anyway, overwriting whatever it raced with.
+    LastWriterWins,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(ConflictPolicy::Reject),
+            "last-writer-wins" => Ok(ConflictPolicy::LastWriterWins),
+            _ => Err(format!(
+                "unknown conflict policy '{}', expected reject or last-writer-wins",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub conflicts: ConflictPolicy,
+    /// A file to write every accepted state to, in the helper's binary format.
+    pub persist: Option<PathBuf>,
+    /// Whether clients may push their own changes. If not, they are sent the current state
+    /// back instead, e.g. so that nothing overwrites a recording being replayed.
+    pub accept_updates: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            conflicts: ConflictPolicy::Reject,
+            persist: None,
+            accept_updates: true,
+        }
+    }
+}
+
+/// Hosts a game for helper clients, so that no device has to act as the host.
+///
+/// The server holds the authoritative [`State`] and sends it to every client, first when it
+/// connects and then whenever the state changes, in the same framing [`FrameReader`] reads.
+/// Clients may push their own changes, which are validated and sent back out to every client,
+/// including the one that made them so that it learns the message number they were given.
+pub struct Server {
+    options: Options,
+    shared: Mutex<Shared>,
+}
+
+struct Client {
+    id: usize,
+    peer: SocketAddr,
+    stream: TcpStream,
+}
+
+struct Shared {
+    state: State,
+    frame: Frame,
+    clients: Vec<Client>,
+    next_id: usize,
+}
+
+/// Checks that a state pushed by a client is something the helper could have produced.
+fn validate(state: &State) -> Result<(), String> {
+    if state.round < 0 {
+        return Err(format!("negative round {}", state.round));
+    }
+    if !(0..=7).contains(&state.scenario_level) {
+        return Err(format!(
+            "scenario level {} out of range",
+            state.scenario_level
+        ));
+    }
+    for actor in &state.actors {
+        let instances = match actor {
+            Actor::Player(player) => {
+                if player.hp < 0 || player.hp_max < 0 {
+                    return Err(format!("negative hit points for {}", player.name));
+                }
+                &player.instances
+            }
+            Actor::Monster(monster) => &monster.instances,
+        };
+        for instance in instances {
+            if instance.hp < 0 || instance.hp_max < 0 {
+                return Err(format!(
+                    "negative hit points for instance {}",
+                    instance.number
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Server {
+    pub fn new(state: State, options: Options) -> Result<Self, state::Error> {
+        let frame = Frame {
+            header: String::new(),
+            message_number: Some(0),
+            data: state::to_bytes(&state)?,
+        };
+        Ok(Server {
+            options,
+            shared: Mutex::new(Shared {
+                state,
+                frame,
+                clients: Vec::new(),
+                next_id: 0,
+            }),
+        })
+    }
+
+    pub fn state(&self) -> State {
+        self.shared.lock().unwrap().state.clone()
+    }
+
+    pub fn message_number(&self) -> i32 {
+        let shared = self.shared.lock().unwrap();
+        shared.frame.message_number.unwrap_or_default()
+    }
+
+    /// How many clients are connected.
+    pub fn clients(&self) -> usize {
+        self.shared.lock().unwrap().clients.len()
+    }
+
+    /// Broadcasts `frame` exactly as it is, e.g. one read back from a session log, and makes its
+    /// state and message number the current ones. Frames without a state are ignored. A frame
+    /// whose state doesn't decode is still passed on, but the current state is kept.
+    pub fn replay(&self, frame: Frame) {
+        if frame.message_number.is_none() {
+            return;
+        }
+        let mut shared = self.shared.lock().unwrap();
+        match frame.state() {
+            Ok(state) => shared.state = state,
+            Err(err) => eprintln!("unable to decode replayed state: {}", err),
+        }
+        shared.frame = frame;
+        shared.broadcast();
+    }
+
+    /// Replaces the state and broadcasts it to all clients, returning its message number.
+    pub fn update(&self, state: State) -> io::Result<i32> {
+        let data = state::to_bytes(&state).map_err(invalid_data)?;
+        let mut shared = self.shared.lock().unwrap();
+        self.commit(&mut shared, state, data)
+    }
+
+    /// Accepts clients on `listener` until it fails, handling each one on its own thread.
+    pub fn serve(self: &Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            match self.add_client(stream) {
+                Ok((id, reader)) => {
+                    let server = self.clone();
+                    std::thread::spawn(move || server.handle_client(id, reader));
+                }
+                Err(err) => eprintln!("unable to add client: {}", err),
+            }
+        }
+        Ok(())
+    }
+
+    fn add_client(&self, mut stream: TcpStream) -> io::Result<(usize, TcpStream)> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let peer = stream.peer_addr()?;
+        let reader = stream.try_clone()?;
+        let mut shared = self.shared.lock().unwrap();
+        shared.frame.write_to(&mut stream)?;
+        let id = shared.next_id;
+        shared.next_id += 1;
+        shared.clients.push(Client { id, peer, stream });
+        eprintln!("client {} connected", peer);
+        Ok((id, reader))
+    }
+
+    fn handle_client(&self, id: usize, stream: TcpStream) {
+        let mut reader = FrameReader::new(BufReader::new(stream));
+        let err = loop {
+            match reader.read_frame() {
+                Ok(Some(frame)) => {
+                    if let Err(err) = self.apply(id, frame) {
+                        eprintln!("rejected update from client {}: {}", id, err);
+                    }
+                }
+                Ok(None) => break None,
+                Err(err) => break Some(err),
+            }
+        };
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(i) = shared.clients.iter().position(|client| client.id == id) {
+            let client = shared.clients.remove(i);
+            match err {
+                Some(err) => eprintln!("client {} disconnected: {}", client.peer, err),
+                None => eprintln!("client {} disconnected", client.peer),
+            }
+        }
+    }
+
+    /// Applies a state pushed by client `id`.
+    fn apply(&self, id: usize, frame: Frame) -> io::Result<()> {
+        let message_number = match frame.message_number {
+            Some(message_number) => message_number,
+            None => return Ok(()),
+        };
+        if !self.options.accept_updates {
+            self.shared.lock().unwrap().resend(id)?;
+            return Err(invalid_data("updates from clients are not accepted"));
+        }
+        let state = frame.state().map_err(invalid_data)?;
+        validate(&state).map_err(invalid_data)?;
+
+        let mut shared = self.shared.lock().unwrap();
+        let current = shared.frame.message_number.unwrap_or_default();
+        if message_number <= current && self.options.conflicts == ConflictPolicy::Reject {
+            shared.resend(id)?;
+            return Err(invalid_data(format!(
+                "stale message number {}, current is {}",
+                message_number, current
+            )));
+        }
+        self.commit(&mut shared, state, frame.data)?;
+        Ok(())
+    }
+
+    /// Makes `state` the current state and sends it to every client, including the one it came
+    /// from so that it learns the message number it was given.
+    fn commit(&self, shared: &mut Shared, state: State, data: Vec<u8>) -> io::Result<i32> {
+        if let Some(path) = &self.options.persist {
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, &data)?;
+            std::fs::rename(&tmp, path)?;
+        }
+        let message_number = shared.frame.message_number.unwrap_or_default() + 1;
+        shared.state = state;
+        shared.frame.message_number = Some(message_number);
+        shared.frame.data = data;
+        shared.broadcast();
+        Ok(message_number)
+    }
+}
+
+impl Shared {
+    /// Sends the current state to client `id` again.
+    fn resend(&mut self, id: usize) -> io::Result<()> {
+        let frame = &self.frame;
+        if let Some(client) = self.clients.iter_mut().find(|client| client.id == id) {
+            frame.write_to(&mut client.stream)?;
+        }
+        Ok(())
+    }
+
+    fn broadcast(&mut self) {
+        let frame = &self.frame;
+        self.clients
+            .retain_mut(|client| match frame.write_to(&mut client.stream) {
+                Ok(()) => true,
+                Err(err) => {
+                    eprintln!("client {} disconnected: {}", client.peer, err);
+                    false
+                }
+            });
+    }
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::example_state;
+    use std::io::Write;
+    use std::time::Instant;
+
+    fn start(options: Options) -> (Arc<Server>, State, SocketAddr) {
+        let state = example_state();
+        let server = Arc::new(Server::new(state.clone(), options).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        {
+            let server = server.clone();
+            std::thread::spawn(move || server.serve(listener));
+        }
+        (server, state, addr)
+    }
+
+    fn connect(addr: SocketAddr) -> FrameReader<TcpStream> {
+        connect_at(addr, 0)
+    }
+
+    fn connect_at(addr: SocketAddr, message_number: i32) -> FrameReader<TcpStream> {
+        let mut client = FrameReader::new(TcpStream::connect(addr).unwrap());
+        let frame = client.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(message_number));
+        client
+    }
+
+    fn push(client: &mut FrameReader<TcpStream>, message_number: i32, state: &State) {
+        Frame {
+            header: String::new(),
+            message_number: Some(message_number),
+            data: state::to_bytes(state).unwrap(),
+        }
+        .write_to(client.get_mut())
+        .unwrap();
+        client.get_mut().flush().unwrap();
+    }
+
+    #[test]
+    fn broadcast() {
+        let (server, state, addr) = start(Options::default());
+        let mut clients = vec![connect(addr), connect(addr)];
+
+        let mut next = state.clone();
+        next.round += 1;
+        assert_eq!(server.update(next.clone()).unwrap(), 1);
+        for client in &mut clients {
+            let frame = client.read_frame().unwrap().unwrap();
+            assert_eq!(frame.message_number, Some(1));
+            assert_eq!(frame.state().unwrap(), next);
+        }
+        assert_eq!(server.state(), next);
+    }
+
+    #[test]
+    fn client_update() {
+        let path = std::env::temp_dir().join(format!("hhh-{}.bin", std::process::id()));
+        let (server, state, addr) = start(Options {
+            persist: Some(path.clone()),
+            ..Options::default()
+        });
+        let mut a = connect(addr);
+        let mut b = connect(addr);
+
+        let mut next = state.clone();
+        next.round += 1;
+        push(&mut a, 1, &next);
+        let frame = b.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(1));
+        assert_eq!(frame.state().unwrap(), next);
+        let persisted: State = state::from_bytes(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(persisted, next);
+        std::fs::remove_file(&path).unwrap();
+
+        // The client that made the update is told the message number it was given.
+        let frame = a.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(1));
+        assert_eq!(frame.state().unwrap(), next);
+        assert_eq!(server.state(), next);
+    }
+
+    #[test]
+    fn stale_update() {
+        let (server, state, addr) = start(Options::default());
+        let mut a = connect(addr);
+        let mut b = connect(addr);
+
+        let mut from_a = state.clone();
+        from_a.round = 10;
+        push(&mut a, 1, &from_a);
+        assert_eq!(b.read_frame().unwrap().unwrap().message_number, Some(1));
+
+        let mut from_b = state.clone();
+        from_b.round = 20;
+        push(&mut b, 1, &from_b);
+        let frame = b.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(1));
+        assert_eq!(frame.state().unwrap(), from_a);
+        assert_eq!(server.state(), from_a);
+    }
+
+    #[test]
+    fn last_writer_wins() {
+        let (server, state, addr) = start(Options {
+            conflicts: ConflictPolicy::LastWriterWins,
+            ..Options::default()
+        });
+        let mut a = connect(addr);
+        let mut b = connect(addr);
+
+        let mut from_a = state.clone();
+        from_a.round = 10;
+        push(&mut a, 1, &from_a);
+        assert_eq!(b.read_frame().unwrap().unwrap().message_number, Some(1));
+
+        assert_eq!(a.read_frame().unwrap().unwrap().message_number, Some(1));
+
+        let mut from_b = state.clone();
+        from_b.round = 20;
+        push(&mut b, 1, &from_b);
+        let frame = a.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(2));
+        assert_eq!(frame.state().unwrap(), from_b);
+        assert_eq!(server.state(), from_b);
+    }
+
+    #[test]
+    fn invalid_update() {
+        let (server, state, addr) = start(Options::default());
+        let mut a = connect(addr);
+
+        let mut invalid = state.clone();
+        invalid.scenario_level = 12;
+        push(&mut a, 1, &invalid);
+        let mut valid = state.clone();
+        valid.round = 6;
+        push(&mut a, 1, &valid);
+        // Wait for the valid update to go through, it's handled after the invalid one.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.message_number() == 0 {
+            assert!(Instant::now() < deadline, "the valid update never arrived");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(server.state(), valid);
+    }
+
+    #[test]
+    fn replay() {
+        let (server, state, addr) = start(Options::default());
+        let mut a = connect(addr);
+        assert_eq!(server.clients(), 1);
+
+        let recorded = Frame {
+            header: "recorded".to_string(),
+            message_number: Some(42),
+            data: include_bytes!("example_state.bin").to_vec(),
+        };
+        server.replay(recorded.clone());
+        assert_eq!(a.read_frame().unwrap().unwrap(), recorded);
+        assert_eq!(server.message_number(), 42);
+        assert_eq!(server.state(), state);
+
+        let invalid = Frame {
+            message_number: Some(43),
+            data: vec![1, 2, 3],
+            ..recorded
+        };
+        server.replay(invalid.clone());
+        assert_eq!(a.read_frame().unwrap().unwrap(), invalid);
+        assert_eq!(server.message_number(), 43);
+        assert_eq!(server.state(), state);
+    }
+
+    #[test]
+    fn updates_not_accepted() {
+        let (server, state, addr) = start(Options {
+            accept_updates: false,
+            ..Options::default()
+        });
+        let mut a = connect(addr);
+        let mut next = state.clone();
+        next.round = 6;
+        push(&mut a, 1, &next);
+        let frame = a.read_frame().unwrap().unwrap();
+        assert_eq!(frame.message_number, Some(0));
+        assert_eq!(server.state(), state);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::frame::{Frame, FrameReader};
+
+/// The first bytes of every session log, the last one being the format version.
+const MAGIC: &[u8; 6] = b"HHLOG\x01";
+
+/// A frame as it was received during a session.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    /// Milliseconds since the Unix epoch.
+    pub received_at: u64,
+    pub frame: Frame,
+}
+
+impl Entry {
+    /// An entry for `frame`, received just now.
+    pub fn now(frame: Frame) -> Self {
+        Entry {
+            received_at: unix_millis(),
+            frame,
+        }
+    }
+}
+
+/// The current time in milliseconds since the Unix epoch.
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+/// Appends [`Entry`]s to a session log.
+///
+/// A session log starts with a magic number, followed by one record per entry: the time it was
+/// received as a big endian `u64`, then the frame exactly as [`Frame::write_to`] writes it.
+pub struct SessionWriter<W> {
+    inner: W,
+}
+
+impl SessionWriter<File> {
+    /// Opens the log at `path` for appending, creating it if it doesn't exist yet.
+    pub fn append<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+        } else {
+            // Make sure we're not about to append to something else entirely.
+            SessionReader::new(File::open(&path)?)?;
+        }
+        Ok(SessionWriter { inner: file })
+    }
+}
+
+impl<W: Write> SessionWriter<W> {
+    /// Starts a new log, writing its magic number to `inner`.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        Ok(SessionWriter { inner })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes `entry` and flushes it, so that the log is complete up to the last frame even if
+    /// the process is killed.
+    pub fn write_entry(&mut self, entry: &Entry) -> io::Result<()> {
+        let mut record = entry.received_at.to_be_bytes().to_vec();
+        entry.frame.write_to(&mut record)?;
+        self.inner.write_all(&record)?;
+        self.inner.flush()
+    }
+}
+
+/// Reads the [`Entry`]s of a session log written by [`SessionWriter`].
+pub struct SessionReader<R> {
+    frames: FrameReader<R>,
+}
+
+impl<R: Read> SessionReader<R> {
+    /// Checks the magic number at the start of `inner`.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        inner.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a session log",
+            ));
+        }
+        Ok(SessionReader {
+            frames: FrameReader::new(inner),
+        })
+    }
+
+    /// Reads the next entry, or returns `None` at the end of the log.
+    pub fn read_entry(&mut self) -> io::Result<Option<Entry>> {
+        let inner = self.frames.get_mut();
+        let mut received_at = [0; 8];
+        match inner.read(&mut received_at[..1])? {
+            0 => return Ok(None),
+            _ => inner.read_exact(&mut received_at[1..])?,
+        }
+        let frame = self
+            .frames
+            .read_frame()?
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        Ok(Some(Entry {
+            received_at: u64::from_be_bytes(received_at),
+            frame,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for SessionReader<R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                received_at: 1_600_000_000_000,
+                frame: Frame {
+                    header: "state".to_string(),
+                    message_number: Some(7),
+                    data: include_bytes!("example_state.bin").to_vec(),
+                },
+            },
+            Entry {
+                received_at: 1_600_000_002_500,
+                frame: Frame {
+                    header: String::new(),
+                    message_number: None,
+                    data: Vec::new(),
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut writer = SessionWriter::new(Vec::new()).unwrap();
+        let mut ends = Vec::new();
+        for entry in entries() {
+            writer.write_entry(&entry).unwrap();
+            ends.push(writer.inner.len());
+        }
+        let bytes = writer.into_inner();
+        let read: Vec<_> = SessionReader::new(&bytes[..])
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(read, entries());
+
+        for len in MAGIC.len() + 1..bytes.len() {
+            if ends.contains(&len) {
+                continue;
+            }
+            let err = SessionReader::new(&bytes[..len])
+                .unwrap()
+                .find_map(Result::err)
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", len);
+        }
+        assert!(SessionReader::new(&include_bytes!("example_state.bin")[..]).is_err());
+    }
+
+    #[test]
+    fn append() {
+        let path = std::env::temp_dir().join(format!("session-{}.hhlog", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        for entry in entries() {
+            SessionWriter::append(&path)
+                .unwrap()
+                .write_entry(&entry)
+                .unwrap();
+        }
+        let read: Vec<_> = SessionReader::new(File::open(&path).unwrap())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, entries());
+    }
+}
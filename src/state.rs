@@ -1,244 +1,520 @@
 use serde::de::{DeserializeSeed, IntoDeserializer, Visitor};
 use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
+use crate::diff::Target;
+
+/// Defines a fieldless enum that is written as its `u8` discriminant in the binary format and
+/// as its variant name in human readable formats such as JSON, and read back the same way.
+macro_rules! named_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident { $($(#[$variant_meta:meta])* $variant:ident = $value:expr,)* }
+    ) => {
+        $(#[$meta])*
+        pub enum $name {
+            $($(#[$variant_meta])* $variant = $value,)*
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::ser::Serializer,
+            {
+                if serializer.is_human_readable() {
+                    let variant = match self {
+                        $($name::$variant => stringify!($variant),)*
+                    };
+                    serializer.serialize_unit_variant(stringify!($name), *self as u32, variant)
+                } else {
+                    serializer.serialize_u8(*self as u8)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::de::Deserializer<'de>,
+            {
+                use serde::de::{Error, Unexpected};
+
+                if deserializer.is_human_readable() {
+                    let name = String::deserialize(deserializer)?;
+                    $(if name == stringify!($variant) {
+                        return Ok($name::$variant);
+                    })*
+                    Err(D::Error::unknown_variant(&name, &[$(stringify!($variant)),*]))
+                } else {
+                    let value = u8::deserialize(deserializer)?;
+                    $(if value == $value {
+                        return Ok($name::$variant);
+                    })*
+                    Err(D::Error::invalid_value(
+                        Unexpected::Unsigned(value.into()),
+                        &stringify!($name),
+                    ))
+                }
+            }
+        }
+    };
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialOrd, PartialEq)]
 pub struct State {
-    round: i32,
-    scenario_number: i32,
-    scenario_level: i32,
-    track_standees: bool,
-    ability_cards: bool,
-    random_standees: bool,
-    elites_first: bool,
-    expire_conditions: bool,
-    solo: bool,
-    hide_stats: bool,
-    calculate_stats: bool,
-    can_draw: bool,
-    needs_shuffle: bool,
-    player_init: i32,
-    attack_modifiers: Vec<AttackModifier>,
-    attack_modifiers_discard: Vec<AttackModifier>,
-    fire: ElementState,
-    ice: ElementState,
-    air: ElementState,
-    earth: ElementState,
-    light: ElementState,
-    dark: ElementState,
-    removed_abilities: Vec<i32>,
-    bad_omen: i32,
-    ability_decks: Vec<AbilityDeck>,
-    actors: Vec<Actor>,
+    pub round: i32,
+    pub scenario_number: i32,
+    pub scenario_level: i32,
+    pub track_standees: bool,
+    pub ability_cards: bool,
+    pub random_standees: bool,
+    pub elites_first: bool,
+    pub expire_conditions: bool,
+    pub solo: bool,
+    pub hide_stats: bool,
+    pub calculate_stats: bool,
+    pub can_draw: bool,
+    pub needs_shuffle: bool,
+    pub player_init: i32,
+    pub attack_modifiers: Vec<AttackModifier>,
+    pub attack_modifiers_discard: Vec<AttackModifier>,
+    pub fire: ElementState,
+    pub ice: ElementState,
+    pub air: ElementState,
+    pub earth: ElementState,
+    pub light: ElementState,
+    pub dark: ElementState,
+    pub removed_abilities: Vec<i32>,
+    pub bad_omen: i32,
+    pub ability_decks: Vec<AbilityDeck>,
+    pub actors: Vec<Actor>,
+    /// A value the helper writes after the actors. It has only been seen as 0 and what it
+    /// stands for isn't known, but it's kept so that a state is written back byte for byte.
+    pub unknown: i32,
+    /// The summons and monster standees that have ended their turn this round. The helper only
+    /// records it for whole players and monster groups, so this isn't sent over the wire and
+    /// has to be carried over from one state to the next by whoever calls
+    /// [`end_turn`](State::end_turn).
+    #[serde(skip)]
+    pub ended_turns: Vec<Target>,
+}
+
+named_enum! {
+    #[derive(Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
+    #[repr(u8)]
+    pub enum AttackModifier {
+        Zero = 0,
+        Plus1 = 1,
+        Plus2 = 2,
+        Minus1 = 3,
+        Minus2 = 4,
+        Miss = 5,
+        Crit = 6,
+        Bless = 7,
+        Curse = 8,
+    }
+}
+
+named_enum! {
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialOrd, PartialEq)]
+    #[repr(u8)]
+    pub enum ElementState {
+        #[default]
+        Inert = 0,
+        Strong = 1,
+        Waning = 2,
+    }
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
-#[repr(u8)]
-pub enum AttackModifier {
-    Zero = 0,
-    Plus1 = 1,
-    Plus2 = 2,
-    Minus1 = 3,
-    Minus2 = 4,
-    Miss = 5,
-    Crit = 6,
-    Bless = 7,
-    Curse = 8,
+/// One of the six elements whose [`ElementState`] is tracked in a [`State`].
+#[derive(Serialize, Copy, Clone, Debug, Eq, Hash, PartialOrd, PartialEq)]
+pub enum Element {
+    Fire,
+    Ice,
+    Air,
+    Earth,
+    Light,
+    Dark,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
-#[repr(u8)]
-pub enum ElementState {
-    Inert = 0,
-    Strong = 1,
-    Waning = 2,
+impl Element {
+    /// Every element, in the order the helper sends them.
+    pub const ALL: [Element; 6] = [
+        Element::Fire,
+        Element::Ice,
+        Element::Air,
+        Element::Earth,
+        Element::Light,
+        Element::Dark,
+    ];
+}
+
+impl State {
+    /// The state of `element`.
+    pub fn element(&self, element: Element) -> ElementState {
+        match element {
+            Element::Fire => self.fire,
+            Element::Ice => self.ice,
+            Element::Air => self.air,
+            Element::Earth => self.earth,
+            Element::Light => self.light,
+            Element::Dark => self.dark,
+        }
+    }
+
+    pub fn element_mut(&mut self, element: Element) -> &mut ElementState {
+        match element {
+            Element::Fire => &mut self.fire,
+            Element::Ice => &mut self.ice,
+            Element::Air => &mut self.air,
+            Element::Earth => &mut self.earth,
+            Element::Light => &mut self.light,
+            Element::Dark => &mut self.dark,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
 pub struct Ability {
-    value: i32,
+    pub value: i32,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
 pub struct AbilityDeck {
-    id: i32,
-    shuffle: bool,
-    #[serde(deserialize_with = "deserialize_into_ability")]
-    shown_ability: Option<Ability>,
-    abilities: Vec<i32>,
-    abilities_discard: Vec<i32>,
+    pub id: i32,
+    pub shuffle: bool,
+    #[serde(
+        deserialize_with = "deserialize_into_ability",
+        serialize_with = "serialize_from_ability"
+    )]
+    pub shown_ability: Option<Ability>,
+    pub abilities: Vec<i32>,
+    pub abilities_discard: Vec<i32>,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
-#[repr(u8)]
-pub enum MonsterType {
-    Normal = 0,
-    Elite = 1,
-    Summon = 3,
+named_enum! {
+    #[derive(Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
+    #[repr(u8)]
+    pub enum MonsterType {
+        Normal = 0,
+        Elite = 1,
+        Summon = 3,
+    }
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
-#[repr(u8)]
-pub enum Condition {
-    Summoned = 2,
-    Stunned = 3,
-    Immobilized = 4,
-    Disarmed = 5,
-    Wounded = 6,
-    Muddled = 7,
-    Poisoned = 8,
-    Strengthened = 9,
-    Invisible = 10,
+named_enum! {
+    #[derive(Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
+    #[repr(u8)]
+    pub enum Condition {
+        Summoned = 2,
+        Stunned = 3,
+        Immobilized = 4,
+        Disarmed = 5,
+        Wounded = 6,
+        Muddled = 7,
+        Poisoned = 8,
+        Strengthened = 9,
+        Invisible = 10,
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
 pub struct MonsterInstance {
-    number: i32,
-    tpe: MonsterType,
+    pub number: i32,
+    #[serde(rename = "type")]
+    pub tpe: MonsterType,
     // TODO: if MonsterType::Summon, then populate more fields
-    is_new: bool,
-    hp: i32,
-    hp_max: i32,
-    conditions: Vec<Condition>,
-    conditions_expired: Vec<Condition>,
-    conditions_current_turn: Vec<Condition>,
+    pub is_new: bool,
+    pub hp: i32,
+    pub hp_max: i32,
+    pub conditions: Vec<Condition>,
+    pub conditions_expired: Vec<Condition>,
+    pub conditions_current_turn: Vec<Condition>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
 pub struct Player {
-    name: String,
-    character_class: CharacterClass,
-    xp: i32,
-    hp: i32,
-    hp_max: i32,
-    level: i32,
-    loot: i32,
-    initiative: i32,
-    conditions: Vec<Condition>,
-    conditions_expired: Vec<Condition>,
-    conditions_current_turn: Vec<Condition>,
-    exhausted: bool,
-    turn_completed: bool,
-    instances: Vec<MonsterInstance>,
+    pub name: String,
+    pub character_class: CharacterClass,
+    pub xp: i32,
+    pub hp: i32,
+    pub hp_max: i32,
+    pub level: i32,
+    pub loot: i32,
+    pub initiative: i32,
+    pub conditions: Vec<Condition>,
+    pub conditions_expired: Vec<Condition>,
+    pub conditions_current_turn: Vec<Condition>,
+    pub exhausted: bool,
+    pub turn_completed: bool,
+    pub instances: Vec<MonsterInstance>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
 pub struct Monster {
-    id: i32,
-    level: i32,
-    is_normal: bool,
-    is_elite: bool,
-    ability: Ability,
-    turn_completed: bool,
-    instances: Vec<MonsterInstance>,
+    pub id: i32,
+    pub level: i32,
+    pub is_normal: bool,
+    pub is_elite: bool,
+    pub ability: Ability,
+    pub turn_completed: bool,
+    pub instances: Vec<MonsterInstance>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq)]
 pub enum Actor {
     Monster(Monster),
     Player(Player),
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
-#[repr(u8)]
-pub enum CharacterClass {
-    Escort = 0,
-    Objective = 1,
-    Brute = 2,
-    Cragheart = 3,
-    Mindthief = 4,
-    Scoundrel = 5,
-    Spellweaver = 6,
-    Tinkerer = 7,
-    Diviner = 8,
-    TwoMinis = 9,
-    Lightning = 10,
-    AngryFace = 11,
-    Triangles = 12,
-    Moon = 13,
-    CthuluFace = 14,
-    TripleArrow = 15,
-    Saw = 16,
-    MusicNote = 17,
-    Circles = 18,
-    Sun = 19,
+named_enum! {
+    #[derive(Copy, Clone, Debug, Eq, PartialOrd, PartialEq)]
+    #[repr(u8)]
+    pub enum CharacterClass {
+        Escort = 0,
+        Objective = 1,
+        Brute = 2,
+        Cragheart = 3,
+        Mindthief = 4,
+        Scoundrel = 5,
+        Spellweaver = 6,
+        Tinkerer = 7,
+        Diviner = 8,
+        TwoMinis = 9,
+        Lightning = 10,
+        AngryFace = 11,
+        Triangles = 12,
+        Moon = 13,
+        CthuluFace = 14,
+        TripleArrow = 15,
+        Saw = 16,
+        MusicNote = 17,
+        Circles = 18,
+        Sun = 19,
+    }
 }
 
+/// Reads a varint from the start of `buf`, returning how many bytes it took up and its value.
+/// Returns `None` if `buf` ends before the varint does, or if it's longer than five bytes.
 pub fn read_varint(buf: &[u8]) -> Option<(usize, i32)> {
     let mut res: i32 = 0;
     for (i, b) in buf.iter().take(5).enumerate() {
         res |= ((b & 0x7F) as i32) << (i * 7) as i32;
         if (b & 0x80) == 0 {
-            return Some(dbg!((i + 1, res)));
+            return Some((i + 1, res));
         }
     }
     None
 }
 
+/// Appends `value` to `buf` as a varint, the inverse of [`read_varint`].
+pub fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        let b = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(b);
+            return;
+        }
+        buf.push(b | 0x80);
+    }
+}
+
+enum Segment {
+    Struct(&'static str),
+    Field(&'static str),
+    Variant(&'static str),
+    Index(usize),
+}
+
 struct Deserializer<'de> {
     input: &'de [u8],
     pos: usize,
+    path: Vec<Segment>,
+    last_tag: Option<(usize, u8)>,
 }
 
 impl<'de> Deserializer<'de> {
     pub fn from_bytes(input: &'de [u8]) -> Self {
-        Deserializer { input, pos: 0 }
+        Deserializer {
+            input,
+            pos: 0,
+            path: Vec::new(),
+            last_tag: None,
+        }
+    }
+
+    fn path(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                Segment::Struct(name) => path.push_str(name),
+                Segment::Field(name) | Segment::Variant(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(name);
+                }
+                Segment::Index(i) => path.push_str(&format!("[{}]", i)),
+            }
+        }
+        path
+    }
+
+    fn error_at(&self, pos: usize, kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            pos: Some(pos),
+            path: self.path(),
+        }
+    }
+
+    /// Attaches the position and path of the value starting at `start` to errors that were
+    /// raised without them, e.g. through `serde::de::Error::custom`.
+    fn locate(&self, err: Error, start: usize) -> Error {
+        if err.pos.is_some() {
+            return err;
+        }
+        match (err.kind, self.last_tag) {
+            // The only thing read for the value was a tag, which its visitor rejected.
+            (ErrorKind::Custom(_), Some((pos, tag))) if pos == start => {
+                self.error_at(start, ErrorKind::UnknownTag(tag))
+            }
+            (kind, _) => self.error_at(start, kind),
+        }
     }
 
     fn parse_varint(&mut self) -> Result<i32, Error> {
-        let (len, val) = read_varint(&self.input[self.pos..]).ok_or(Error::Empty)?;
+        let remaining = &self.input[self.pos..];
+        let (len, val) = read_varint(remaining).ok_or_else(|| {
+            let kind = if remaining.len() < 5 {
+                ErrorKind::Eof
+            } else {
+                ErrorKind::OverlongVarint
+            };
+            self.error_at(self.pos, kind)
+        })?;
         self.pos += len;
         Ok(val)
     }
 
-    fn read_byte(&mut self) -> u8 {
-        let res = self.input[self.pos];
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        let res = *self
+            .input
+            .get(self.pos)
+            .ok_or_else(|| self.error_at(self.pos, ErrorKind::Eof))?;
         self.pos += 1;
-        res
+        Ok(res)
     }
 
     fn parse_bool(&mut self) -> Result<bool, Error> {
-        Ok(self.read_byte() == 1)
+        Ok(self.read_byte()? == 1)
     }
 
-    fn parse_str(&mut self) -> Result<&'de str, Error> {
-        dbg!("parse_str");
-        let b = self.read_byte();
+    /// Reads a string as written by Kryo's `Output::writeString`. Short ASCII strings are
+    /// written as-is with the high bit set on the last byte, everything else is prefixed by
+    /// its length in UTF-16 code units, where 0 means null and 1 means empty.
+    fn parse_str(&mut self) -> Result<String, Error> {
+        let start = self.pos;
+        let b = self.read_byte()?;
         if (b & 0x80) == 0 {
-            // ASCII for some reason
-            return Ok("");
+            let mut s = String::new();
+            s.push(b as char);
+            loop {
+                let b = self.read_byte()?;
+                s.push((b & 0x7F) as char);
+                if (b & 0x80) != 0 {
+                    return Ok(s);
+                }
+            }
         }
         let mut len = (b & 0x3F) as u32;
         if (b & 0x40) != 0 {
-            //            len = std::iter::from_fn(|| Some(self.read_byte()))
-            //                .take_while(|b| (b & 0x80) != 0)
-            //                .take(4)
-            //                .enumerate()
-            //                .fold(len, |acc, (i, b)| acc | (b & 0x7F) << (7 * i as u8 - 1));
-            let b = self.read_byte() as u32;
-            len |= (b & 0x7F) << 6;
-            if (b & 0x80) != 0 {
-                let b = self.read_byte() as u32;
-                len |= (b & 0x7F) << 13;
-                if (b & 0x80) != 0 {
-                    let b = self.read_byte() as u32;
-                    len |= (b & 0x7F) << 20;
-                    if (b & 0x80) != 0 {
-                        let b = self.read_byte() as u32;
-                        len |= (b & 0x7F) << 27;
-                    }
+            for shift in &[6, 13, 20, 27] {
+                let b = self.read_byte()? as u32;
+                len |= (b & 0x7F) << shift;
+                if (b & 0x80) == 0 {
+                    break;
                 }
             }
         }
         if len <= 1 {
-            Ok("")
+            return Ok(String::new());
+        }
+        let char_count = len as usize - 1;
+        let mut chars = Vec::with_capacity(char_count.min(self.input.len() - self.pos));
+        for _ in 0..char_count {
+            let (c, n) = decode_utf8_char(&self.input[self.pos..])
+                .map_err(|kind| self.error_at(self.pos, kind))?;
+            chars.push(c);
+            self.pos += n;
+        }
+        String::from_utf16(&chars).map_err(|_| self.error_at(start, ErrorKind::InvalidUtf8))
+    }
+}
+
+/// Decodes a single UTF-16 code unit from Java's modified UTF-8, returning it along with the
+/// number of bytes it took up.
+fn decode_utf8_char(bytes: &[u8]) -> Result<(u16, usize), ErrorKind> {
+    let byte = |i: usize| bytes.get(i).map(|&b| b as u16).ok_or(ErrorKind::Eof);
+    let b = byte(0)?;
+    match b >> 4 {
+        0..=7 => Ok((b, 1)),
+        12 | 13 => Ok(((b & 0x1F) << 6 | (byte(1)? & 0x3F), 2)),
+        14 => Ok((
+            (b & 0x0F) << 12 | (byte(1)? & 0x3F) << 6 | (byte(2)? & 0x3F),
+            3,
+        )),
+        _ => Err(ErrorKind::InvalidUtf8),
+    }
+}
+
+/// Appends a single UTF-16 code unit to `buf` in Java's modified UTF-8.
+fn encode_utf8_char(buf: &mut Vec<u8>, c: u16) {
+    if c <= 0x7F {
+        buf.push(c as u8);
+    } else if c > 0x7FF {
+        buf.push((0xE0 | c >> 12 & 0x0F) as u8);
+        buf.push((0x80 | c >> 6 & 0x3F) as u8);
+        buf.push((0x80 | c & 0x3F) as u8);
+    } else {
+        buf.push((0xC0 | c >> 6 & 0x1F) as u8);
+        buf.push((0x80 | c & 0x3F) as u8);
+    }
+}
+
+/// Encodes a string like Java's `DataOutput::writeUTF`, without its length prefix.
+pub fn write_utf(buf: &mut Vec<u8>, s: &str) {
+    for c in s.encode_utf16() {
+        if c == 0 {
+            buf.extend_from_slice(&[0xC0, 0x80]);
         } else {
-            std::str::from_utf8(&self.input[self.pos - len as usize + 1..self.pos])
-                .map_err(|err| serde::de::Error::custom(err.to_string()))
+            encode_utf8_char(buf, c);
         }
     }
 }
 
+/// Decodes a string written by Java's `DataOutput::writeUTF`, without its length prefix.
+pub fn read_utf(bytes: &[u8]) -> Result<String, Error> {
+    let mut chars = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (c, n) = decode_utf8_char(&bytes[pos..]).map_err(|kind| Error {
+            kind,
+            pos: Some(pos),
+            path: String::new(),
+        })?;
+        chars.push(c);
+        pos += n;
+    }
+    String::from_utf16(&chars).map_err(|_| Error {
+        kind: ErrorKind::InvalidUtf8,
+        pos: Some(0),
+        path: String::new(),
+    })
+}
+
+/// Decodes a value, typically a [`State`], from the helper's binary format.
 pub fn from_bytes<'a, T>(bytes: &'a [u8]) -> Result<T, Error>
 where
     T: Deserialize<'a>,
@@ -249,35 +525,138 @@ where
 }
 
 #[derive(Debug)]
-pub enum Error {
-    Empty,
+pub enum ErrorKind {
+    /// The input ended in the middle of a value.
+    Eof,
+    /// A string wasn't valid (modified) UTF-8.
+    InvalidUtf8,
+    /// An enum tag that doesn't correspond to any known variant.
+    UnknownTag(u8),
+    /// A varint that didn't terminate within five bytes.
+    OverlongVarint,
+    /// A negative sequence length.
+    InvalidLength(i32),
+    Custom(String),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    pos: Option<usize>,
+    path: String,
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The byte offset of the value that failed to decode, if the error came from decoding.
+    pub fn pos(&self) -> Option<usize> {
+        self.pos
+    }
+
+    /// The struct and field being decoded, e.g. `State.actors[3].Monster.instances[0].type`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            pos: None,
+            path: String::new(),
+        }
+    }
 }
 
 impl serde::de::Error for Error {
     fn custom<T: std::fmt::Display>(desc: T) -> Error {
-        // ErrorKind::Custom(desc.to_string()).into()
-        dbg!(desc.to_string());
-        Error::Empty
+        ErrorKind::Custom(desc.to_string()).into()
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: std::fmt::Display>(desc: T) -> Error {
+        ErrorKind::Custom(desc.to_string()).into()
     }
 }
 
 impl std::error::Error for Error {}
 
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Eof => f.write_str("unexpected end of input"),
+            ErrorKind::InvalidUtf8 => f.write_str("invalid UTF-8"),
+            ErrorKind::UnknownTag(tag) => write!(f, "unknown enum tag {}", tag),
+            ErrorKind::OverlongVarint => f.write_str("varint longer than five bytes"),
+            ErrorKind::InvalidLength(len) => write!(f, "invalid sequence length {}", len),
+            ErrorKind::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Error::Empty => "empty",
-            }
-        )
+        write!(f, "{}", self.kind)?;
+        if let Some(pos) = self.pos {
+            write!(f, " at byte {}", pos)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+        Ok(())
     }
 }
 
-impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
+struct Access<'a, 'de> {
+    deserializer: &'a mut Deserializer<'de>,
+    fields: Option<&'static [&'static str]>,
+    index: usize,
+    len: usize,
+}
+
+impl<'de> serde::de::SeqAccess<'de> for Access<'_, 'de> {
     type Error = Error;
 
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        if self.len > 0 {
+            self.len -= 1;
+            let segment = match self.fields {
+                Some(fields) => Segment::Field(fields[self.index]),
+                None => Segment::Index(self.index),
+            };
+            self.index += 1;
+            self.deserializer.path.push(segment);
+            let start = self.deserializer.pos;
+            self.deserializer.last_tag = None;
+            let value = serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)
+                .map_err(|err| self.deserializer.locate(err, start))?;
+            self.deserializer.path.pop();
+            Ok(Some(value))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
@@ -324,7 +703,10 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u8(self.parse_varint()? as u8)
+        let pos = self.pos;
+        let tag = self.parse_varint()? as u8;
+        self.last_tag = Some((pos, tag));
+        visitor.visit_u8(tag)
     }
 
     fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -369,18 +751,18 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
         unimplemented!()
     }
 
-    fn deserialize_str<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_string(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_borrowed_str(dbg!(self.parse_str())?)
+        visitor.visit_string(self.parse_str()?)
     }
 
     fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -437,7 +819,16 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        let pos = self.pos;
         let len: i32 = serde::Deserialize::deserialize(&mut *self)?;
+        // Every element takes at least one byte, so a length beyond the remaining input can be
+        // rejected up front instead of trusting it for allocations.
+        if len < 0 {
+            return Err(self.error_at(pos, ErrorKind::InvalidLength(len)));
+        }
+        if len as usize > self.input.len() - self.pos {
+            return Err(self.error_at(pos, ErrorKind::Eof));
+        }
 
         self.deserialize_tuple(len as usize, visitor)
     }
@@ -446,35 +837,10 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        struct Access<'a, 'de> {
-            deserializer: &'a mut Deserializer<'de>,
-            len: usize,
-        }
-
-        impl<'a, 'de> serde::de::SeqAccess<'de> for Access<'a, 'de> {
-            type Error = Error;
-
-            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
-            where
-                T: serde::de::DeserializeSeed<'de>,
-            {
-                if self.len > 0 {
-                    self.len -= 1;
-                    let value =
-                        serde::de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
-                    Ok(Some(value))
-                } else {
-                    Ok(None)
-                }
-            }
-
-            fn size_hint(&self) -> Option<usize> {
-                Some(self.len)
-            }
-        }
-
         visitor.visit_seq(Access {
             deserializer: self,
+            fields: None,
+            index: 0,
             len,
         })
     }
@@ -500,20 +866,33 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_tuple(fields.len(), visitor)
+        let root = self.path.is_empty();
+        if root {
+            self.path.push(Segment::Struct(name));
+        }
+        let value = visitor.visit_seq(Access {
+            deserializer: &mut *self,
+            fields: Some(fields),
+            index: 0,
+            len: fields.len(),
+        })?;
+        if root {
+            self.path.pop();
+        }
+        Ok(value)
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
@@ -521,9 +900,10 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         struct Enum<'a, 'de> {
             deserializer: &'a mut Deserializer<'de>,
+            variants: &'static [&'static str],
         }
 
-        impl<'de, 'a> serde::de::EnumAccess<'de> for Enum<'a, 'de> {
+        impl<'de> serde::de::EnumAccess<'de> for Enum<'_, 'de> {
             type Error = Error;
             type Variant = Self;
 
@@ -531,14 +911,21 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             where
                 V: serde::de::DeserializeSeed<'de>,
             {
-                let variant = self.deserializer.read_byte();
-                dbg!(variant);
-                let val = seed.deserialize(variant.into_deserializer())?;
+                let pos = self.deserializer.pos;
+                let variant = self.deserializer.read_byte()?;
+                let val = seed
+                    .deserialize(variant.into_deserializer())
+                    .map_err(|_: Error| {
+                        self.deserializer
+                            .error_at(pos, ErrorKind::UnknownTag(variant))
+                    })?;
+                let name = self.variants.get(variant as usize).copied().unwrap_or("?");
+                self.deserializer.path.push(Segment::Variant(name));
                 Ok((val, self))
             }
         }
 
-        impl<'de, 'a> serde::de::VariantAccess<'de> for Enum<'a, 'de> {
+        impl<'de> serde::de::VariantAccess<'de> for Enum<'_, 'de> {
             type Error = Error;
 
             fn unit_variant(self) -> Result<(), Error> {
@@ -549,7 +936,9 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             where
                 T: DeserializeSeed<'de>,
             {
-                seed.deserialize(self.deserializer)
+                let value = seed.deserialize(&mut *self.deserializer)?;
+                self.deserializer.path.pop();
+                Ok(value)
             }
 
             // Tuple variants are represented in JSON as `{ NAME: [DATA...] }` so
@@ -558,7 +947,13 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             where
                 V: Visitor<'de>,
             {
-                serde::de::Deserializer::deserialize_tuple(self.deserializer, len, visitor)
+                let value = serde::de::Deserializer::deserialize_tuple(
+                    &mut *self.deserializer,
+                    len,
+                    visitor,
+                )?;
+                self.deserializer.path.pop();
+                Ok(value)
             }
 
             fn struct_variant<V>(
@@ -569,11 +964,20 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             where
                 V: Visitor<'de>,
             {
-                serde::de::Deserializer::deserialize_tuple(self.deserializer, fields.len(), visitor)
+                let value = serde::de::Deserializer::deserialize_tuple(
+                    &mut *self.deserializer,
+                    fields.len(),
+                    visitor,
+                )?;
+                self.deserializer.path.pop();
+                Ok(value)
             }
         }
 
-        visitor.visit_enum(Enum { deserializer: self })
+        visitor.visit_enum(Enum {
+            deserializer: self,
+            variants,
+        })
     }
 
     fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -591,42 +995,419 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-fn deserialize_into_ability<'de, D>(deserializer: D) -> Result<Option<Ability>, D::Error>
+struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    fn write_varint(&mut self, value: i32) {
+        write_varint(&mut self.output, value);
+    }
+
+    fn write_byte(&mut self, b: u8) {
+        self.output.push(b);
+    }
+
+    fn write_bool(&mut self, v: bool) {
+        self.write_byte(if v { 1 } else { 0 });
+    }
+
+    fn write_utf8_length(&mut self, len: u32) {
+        if len >> 6 == 0 {
+            self.write_byte((len | 0x80) as u8);
+            return;
+        }
+        self.write_byte((len | 0x40 | 0x80) as u8);
+        let mut len = len >> 6;
+        while len >> 7 != 0 {
+            self.write_byte((len | 0x80) as u8);
+            len >>= 7;
+        }
+        self.write_byte(len as u8);
+    }
+
+    fn write_str(&mut self, v: &str) {
+        let char_count = v.encode_utf16().count();
+        if char_count == 0 {
+            // The helper sends missing strings (e.g. unnamed players) as null, which is what
+            // an empty string decodes from.
+            self.write_byte(0x80);
+        } else if char_count > 1 && char_count < 64 && v.is_ascii() {
+            // ASCII strings are written as-is, with the high bit set on the last byte.
+            self.output.extend_from_slice(v.as_bytes());
+            if let Some(last) = self.output.last_mut() {
+                *last |= 0x80;
+            }
+        } else {
+            self.write_utf8_length(char_count as u32 + 1);
+            for c in v.encode_utf16() {
+                encode_utf8_char(&mut self.output, c);
+            }
+        }
+    }
+}
+
+/// Encodes a value, typically a [`State`], in the helper's binary format.
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>, Error>
 where
-    D: serde::de::Deserializer<'de>,
+    T: Serialize,
 {
-    struct AbilityVisitor;
+    let mut serializer = Serializer { output: Vec::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
 
-    impl<'de> Visitor<'de> for AbilityVisitor {
-        type Value = Ability;
+fn unsupported(tpe: &str) -> Error {
+    serde::ser::Error::custom(format!("{} is not supported by the helper format", tpe))
+}
 
-        fn expecting<'a>(&self, f: &mut std::fmt::Formatter<'a>) -> std::fmt::Result {
-            f.write_str("a varint")
-        }
+impl serde::Serializer for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
 
-        fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E>
-        where
-            E: serde::de::Error,
-        {
-            Ok(Ability {
-                value: if value == 0 { 0 } else { value - 1 },
-            })
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = serde::ser::Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write_bool(v);
+        Ok(())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<(), Error> {
+        Err(unsupported("i8"))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<(), Error> {
+        Err(unsupported("i16"))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.write_varint(v);
+        Ok(())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), Error> {
+        Err(unsupported("i64"))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.write_varint(v as i32);
+        Ok(())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<(), Error> {
+        Err(unsupported("u16"))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<(), Error> {
+        Err(unsupported("u32"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), Error> {
+        Err(unsupported("u64"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), Error> {
+        Err(unsupported("f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+        Err(unsupported("f64"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<(), Error> {
+        Err(unsupported("char"))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_str(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(unsupported("none"))
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported("some"))
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(unsupported("unit"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(unsupported("unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        Err(serde::ser::Error::custom("unit variants are not supported"))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported("newtype struct"))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_byte(variant_index as u8);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        let len =
+            len.ok_or_else(|| <Error as serde::ser::Error>::custom("sequence length is required"))?;
+        self.write_varint(len as i32);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(unsupported("tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        self.write_byte(variant_index as u8);
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(unsupported("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        self.write_byte(variant_index as u8);
+        Ok(self)
+    }
+}
+
+impl serde::ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeStructVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct AbilityVisitor;
+
+impl<'de> Visitor<'de> for AbilityVisitor {
+    type Value = Ability;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a varint")
+    }
+
+    fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        if value < 0 {
+            return Err(E::invalid_value(
+                serde::de::Unexpected::Signed(value.into()),
+                &self,
+            ));
         }
+        Ok(Ability {
+            value: if value == 0 { 0 } else { value - 1 },
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Ability {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        deserializer.deserialize_i32(AbilityVisitor)
     }
+}
 
-    impl<'de> Deserialize<'de> for Ability {
-        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: serde::de::Deserializer<'de>,
-        {
-            deserializer.deserialize_i32(AbilityVisitor)
+impl Serialize for Ability {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_i32(self.value)
+        } else {
+            serializer.serialize_i32(if self.value == 0 { 0 } else { self.value + 1 })
         }
     }
+}
 
+fn deserialize_into_ability<'de, D>(deserializer: D) -> Result<Option<Ability>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
     let v = deserializer.deserialize_i32(AbilityVisitor)?;
     Ok(Some(v).filter(|a| a.value != 0))
 }
 
+fn serialize_from_ability<S>(ability: &Option<Ability>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::ser::Serializer,
+{
+    match ability {
+        Some(ability) => ability.serialize(serializer),
+        None if serializer.is_human_readable() => serializer.serialize_none(),
+        None => serializer.serialize_i32(0),
+    }
+}
+
+/// The state in `example_state.bin`, scenario 5 at level 1, which the tests build on.
+#[cfg(test)]
+pub(crate) fn example_state() -> State {
+    from_bytes(include_bytes!("example_state.bin")).unwrap()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -923,15 +1704,33 @@ mod test {
                         instances: vec![],
                     },),
                 ],
+                unknown: 0,
+                ended_turns: Vec::new(),
             }
         );
     }
 
+    #[test]
+    fn state_round_trip() {
+        let bytes = include_bytes!("example_state.bin");
+        let state: State = from_bytes(bytes).unwrap();
+        assert_eq!(to_bytes(&state).unwrap(), bytes.to_vec());
+    }
+
+    #[test]
+    fn varint() {
+        for &value in &[0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, i32::MAX, -1] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            assert_eq!(read_varint(&buf), Some((buf.len(), value)));
+        }
+    }
+
     #[test]
     fn player() {
         let x: Player = from_bytes(&[
-            0x0, /* name */
-            0x2, /* class */
+            0x80, /* name */
+            0x2,  /* class */
             0xE, 0xE, 0x8, 0x2, 0x1, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0,
         ])
         .unwrap();
@@ -985,9 +1784,9 @@ mod test {
     #[test]
     fn actor() {
         let x: Actor = from_bytes(&[
-            0x1, /* enum indicator */
-            0x0, /* name */
-            0x2, /* class */
+            0x1,  /* enum indicator */
+            0x80, /* name */
+            0x2,  /* class */
             0xE, 0xE, 0x8, 0x2, 0x1, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0,
         ])
         .unwrap();
@@ -1035,4 +1834,153 @@ mod test {
             })
         );
     }
+
+    #[test]
+    fn unknown_condition() {
+        let err = from_bytes::<Player>(&[
+            0x80, /* name */
+            0x2,  /* class */
+            0xE, 0xE, 0x8, 0x2, 0x1, 0x0, /* conditions */ 0x2, 0x4, 0x2A,
+        ])
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownTag(0x2A)));
+        assert_eq!(err.pos(), Some(10));
+        assert_eq!(err.path(), "Player.conditions[1]");
+    }
+
+    #[test]
+    fn unknown_actor() {
+        let err = from_bytes::<Actor>(&[0x2, 0x3]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownTag(2)));
+        assert_eq!(err.pos(), Some(0));
+    }
+
+    #[test]
+    fn nested_path() {
+        let err = from_bytes::<Actor>(&[
+            0x0, /* enum indicator */
+            0x3, 0xA, 0x1, 0x0, 0x20, 0x1, /* instances */ 0x1, 0x1, 0x7,
+        ])
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownTag(7)));
+        assert_eq!(err.pos(), Some(9));
+        assert_eq!(err.path(), "Monster.instances[0].type");
+    }
+
+    #[test]
+    fn bad_varint() {
+        let err = from_bytes::<Monster>(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x0]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::OverlongVarint));
+        assert_eq!(err.pos(), Some(0));
+        assert_eq!(err.path(), "Monster.id");
+
+        let err = from_bytes::<Monster>(&[0x3, 0x80]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Eof));
+        assert_eq!(err.pos(), Some(1));
+        assert_eq!(err.path(), "Monster.level");
+        assert_eq!(
+            err.to_string(),
+            "unexpected end of input at byte 1 in Monster.level"
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = include_bytes!("example_state.bin");
+        for len in 0..bytes.len() - 1 {
+            let err = from_bytes::<State>(&bytes[..len]).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Eof), "{}", err);
+        }
+    }
+
+    #[test]
+    fn hostile_length() {
+        let err = from_bytes::<Vec<i32>>(&[0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0x0]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Eof));
+        assert_eq!(err.pos(), Some(0));
+
+        let err = from_bytes::<Vec<i32>>(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x0]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidLength(-1)));
+    }
+
+    #[test]
+    fn strings() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0x80], ""),
+            (&[0x82, 0x4A], "J"),
+            (&[0x46, 0x72, 0x65, 0x64, 0x72, 0x69, 0xEB], "Fredrik"),
+            (&[0x84, 0xC3, 0x85, 0x73, 0x61], "Åsa"),
+            (&[0x84, 0x5A, 0x6F, 0xC3, 0xAB], "Zoë"),
+            (&[0x83, 0xED, 0xA0, 0xBE, 0xED, 0xB6, 0x80], "🦀"),
+        ];
+        for (bytes, expected) in cases {
+            let s: String = from_bytes(bytes).unwrap();
+            assert_eq!(&s, expected);
+            assert_eq!(&to_bytes(&s).unwrap(), bytes);
+        }
+
+        // Kryo only uses the ASCII form for strings shorter than 64 characters.
+        let long = "Bartholomew the Unyielding, Sworn Shield of the Eastern Reaches!";
+        assert_eq!(long.len(), 64);
+        let bytes = to_bytes(&long).unwrap();
+        assert_eq!(&bytes[..2], &[0xC1, 0x01]);
+        assert_eq!(from_bytes::<String>(&bytes).unwrap(), long);
+    }
+
+    #[test]
+    fn player_name() {
+        let x: Player = from_bytes(&[
+            0x84, 0xC3, 0x85, 0x73, 0x61, /* name */
+            0x2,  /* class */
+            0xE, 0xE, 0x8, 0x2, 0x1, 0x0, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0,
+        ])
+        .unwrap();
+        assert_eq!(x.name, "Åsa");
+        assert_eq!(x.character_class, CharacterClass::Brute);
+        assert_eq!(x.xp, 14);
+    }
+
+    #[test]
+    fn utf() {
+        assert_eq!(read_utf(b"state").unwrap(), "state");
+        assert_eq!(read_utf(&[0x5A, 0x6F, 0xC3, 0xAB]).unwrap(), "Zoë");
+        let mut buf = Vec::new();
+        write_utf(&mut buf, "Zoë\0");
+        assert_eq!(buf, [0x5A, 0x6F, 0xC3, 0xAB, 0xC0, 0x80]);
+        assert_eq!(read_utf(&buf).unwrap(), "Zoë\0");
+        assert!(matches!(
+            read_utf(&[0x5A, 0xC3]).unwrap_err().kind(),
+            ErrorKind::Eof
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let state = example_state();
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["round"], 5);
+        assert_eq!(json["attack_modifiers"][0], "Minus1");
+        assert_eq!(json["light"], "Strong");
+        assert_eq!(json["ability_decks"][0]["shown_ability"], 39);
+        assert_eq!(
+            json["ability_decks"][1]["shown_ability"],
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            json["actors"][0]["Player"]["character_class"],
+            "Spellweaver"
+        );
+        assert_eq!(json["actors"][3]["Monster"]["ability"], 39);
+        assert_eq!(
+            json["actors"][5]["Monster"]["instances"][0]["type"],
+            "Elite"
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        let err = to_bytes(&1u64).unwrap_err();
+        assert_eq!(err.to_string(), "u64 is not supported by the helper format");
+        assert!(to_bytes(&Some(1)).is_err());
+    }
 }
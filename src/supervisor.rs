@@ -0,0 +1,183 @@
+use std::io::{self, BufReader};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::command::CommandSender;
+use crate::frame::{Frame, FrameReader};
+
+/// How long sending a command may block before giving up on the connection.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait between attempts to reach the helper server, and how many to make.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Backoff {
+    /// The delay before the first retry, doubled for every consecutive failure.
+    pub initial: Duration,
+    /// The longest delay between two attempts.
+    pub max: Duration,
+    /// How many consecutive failed attempts to allow before giving up, or `None` to retry
+    /// forever.
+    pub max_retries: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            max_retries: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before retry number `retry`, counting from zero.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.initial
+            .checked_mul(1 << retry.min(31))
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+/// Keeps a connection to a helper server alive, reconnecting whenever it drops.
+///
+/// Every frame the helper sends carries the full state, so tracking simply resumes with the
+/// first complete frame on the new connection. A frame that was cut off by the disconnect is
+/// discarded.
+///
+/// Changes can be sent to the server over the same connection with [`Supervisor::commands`].
+pub struct Supervisor {
+    addr: String,
+    backoff: Backoff,
+    commands: CommandSender,
+}
+
+impl Supervisor {
+    pub fn new(addr: impl Into<String>, backoff: Backoff) -> Self {
+        Supervisor {
+            addr: addr.into(),
+            backoff,
+            commands: CommandSender::new(),
+        }
+    }
+
+    /// Returns a handle for sending commands over whichever connection is currently open.
+    pub fn commands(&self) -> CommandSender {
+        self.commands.clone()
+    }
+
+    /// Calls `on_frame` for every frame received, across reconnects.
+    ///
+    /// Returns the last connection error once `max_retries` consecutive attempts have failed
+    /// to produce a frame, or the first error returned by `on_frame`.
+    pub fn run<F>(&self, mut on_frame: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(Frame) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        let mut retries = 0;
+        loop {
+            let mut received = false;
+            let connection = TcpStream::connect(&self.addr).and_then(|stream| {
+                let writer = stream.try_clone()?;
+                writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                Ok((stream, writer))
+            });
+            let err = match connection {
+                Ok((stream, writer)) => {
+                    eprintln!("connected to {}", self.addr);
+                    self.commands.connected(writer);
+                    let mut reader = FrameReader::new(BufReader::new(stream));
+                    loop {
+                        match reader.read_frame() {
+                            Ok(Some(frame)) => {
+                                received = true;
+                                self.commands.received(&frame);
+                                if let Err(err) = on_frame(frame) {
+                                    self.commands.disconnected();
+                                    return Err(err);
+                                }
+                            }
+                            Ok(None) => {
+                                break io::Error::new(
+                                    io::ErrorKind::ConnectionAborted,
+                                    "connection closed by server",
+                                )
+                            }
+                            Err(err) => break err,
+                        }
+                    }
+                }
+                Err(err) => err,
+            };
+            self.commands.disconnected();
+            if received {
+                retries = 0;
+            }
+            if self.backoff.max_retries.is_some_and(|max| retries >= max) {
+                return Err(err.into());
+            }
+            let delay = self.backoff.delay(retries);
+            eprintln!(
+                "connection to {} failed: {}, retrying in {:?}",
+                self.addr, err, delay
+            );
+            std::thread::sleep(delay);
+            retries += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn delay() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+            max_retries: None,
+        };
+        assert_eq!(backoff.delay(0), Duration::from_millis(100));
+        assert_eq!(backoff.delay(1), Duration::from_millis(200));
+        assert_eq!(backoff.delay(3), Duration::from_millis(800));
+        assert_eq!(backoff.delay(4), Duration::from_secs(1));
+        assert_eq!(backoff.delay(100), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for message_number in 1..=2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                Frame {
+                    header: String::new(),
+                    message_number: Some(message_number),
+                    data: include_bytes!("example_state.bin").to_vec(),
+                }
+                .write_to(&mut stream)
+                .unwrap();
+            }
+        });
+
+        let supervisor = Supervisor::new(
+            addr.to_string(),
+            Backoff {
+                initial: Duration::from_millis(1),
+                max: Duration::from_millis(10),
+                max_retries: Some(2),
+            },
+        );
+        let mut received = Vec::new();
+        let result = supervisor.run(|frame| {
+            received.push(frame.message_number);
+            Ok(())
+        });
+        server.join().unwrap();
+        assert!(result.is_err());
+        assert_eq!(received, [Some(1), Some(2)]);
+    }
+}